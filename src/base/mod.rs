@@ -1,11 +1,22 @@
+#[cfg(feature = "web")]
 use wasm_bindgen::prelude::*;
 
+#[cfg(feature = "web")]
 #[wasm_bindgen]
 extern {
     #[wasm_bindgen(js_namespace = console)]
     pub fn log(s: &str);
 }
 
+/**
+ * Without the browser console messages are warnings of the `log` crate, so that programs using the crate
+ * decide whether and where they're shown
+ */
+#[cfg(not(feature = "web"))]
+pub fn log(s: &str) {
+    ::log::warn!("{}", s);
+}
+
 // #[wasm_bindgen]
 // pub fn log(name: &str) {
 //     log(&format!("Hello, {}!", name));
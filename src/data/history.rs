@@ -0,0 +1,349 @@
+use std::collections::VecDeque;
+
+use crate::matrix::Matrix3x3;
+
+use super::{Document, NodeContent, NodeId, Shape, Subtree};
+
+/**
+ * Default number of transactions kept for undo
+ */
+pub const DEFAULT_HISTORY_LIMIT : usize = 100;
+
+/**
+ * Reversible change of the document, applying an edit gives back the edit which reverts it
+ */
+pub enum Edit {
+    Remove(NodeId),
+    Restore(Subtree),
+    SetShape(NodeId, Shape),
+    SetTransform(NodeId, Matrix3x3),
+    SetVisible(NodeId, bool),
+    SetName(NodeId, Option<String>),
+}
+
+impl Edit {
+    /**
+     * Node whose shapes have to be tesselated again after the edit, together with its descendants
+     */
+    pub fn changed_geometry(&self) -> Option<NodeId> {
+        match self {
+            Edit::SetShape(id, _) | Edit::SetTransform(id, _) => Some(*id),
+            _ => None
+        }
+    }
+}
+
+impl Document {
+    /**
+     * Applies the edit and returns its inverse, None if the edit refers to nodes which are not in the document
+     */
+    pub fn apply(&mut self, edit : Edit) -> Option<Edit> {
+        match edit {
+            Edit::Remove(id) => self.remove_node(id).map(Edit::Restore),
+            Edit::Restore(subtree) => {
+                let id = subtree.nodes.first()?.id;
+                self.restore_subtree(subtree).ok()?;
+                Some(Edit::Remove(id))
+            },
+            Edit::SetShape(id, shape) => match &mut self.node_mut(id)?.content {
+                NodeContent::Shape(current) => Some(Edit::SetShape(id, std::mem::replace(current, shape))),
+                NodeContent::Group(_) => None
+            },
+            Edit::SetTransform(id, transform) => {
+                let node = self.node_mut(id)?;
+                Some(Edit::SetTransform(id, std::mem::replace(&mut node.transform, transform)))
+            },
+            Edit::SetVisible(id, visible) => {
+                let node = self.node_mut(id)?;
+                Some(Edit::SetVisible(id, std::mem::replace(&mut node.visible, visible)))
+            },
+            Edit::SetName(id, name) => {
+                let node = self.node_mut(id)?;
+                Some(Edit::SetName(id, std::mem::replace(&mut node.name, name)))
+            },
+        }
+    }
+}
+
+/**
+ * Edits undone or redone together, kept as the edits reverting them in the order they were made
+ */
+struct Transaction {
+    edits : Vec<Edit>,
+}
+
+/**
+ * Undo and redo stacks of transactions. Edits recorded while a transaction is open are grouped into it,
+ * others become transactions of their own. Oldest transactions are dropped over the limit.
+ */
+pub struct History {
+    undo_stack : VecDeque<Transaction>,
+    redo_stack : Vec<Transaction>,
+    open : Option<Transaction>,
+    /** number of nested transactions which are open */
+    depth : usize,
+    limit : usize,
+}
+
+impl History {
+    pub fn new(limit : usize) -> History {
+        History{undo_stack : VecDeque::new(), redo_stack : Vec::new(), open : None, depth : 0, limit}
+    }
+
+    /**
+     * Applies the edit to the document and records it, returns false if it couldn't be applied
+     */
+    pub fn apply(&mut self, document : &mut Document, edit : Edit) -> bool {
+        match document.apply(edit) {
+            Some(inverse) => {
+                self.record(inverse);
+                true
+            },
+            None => false
+        }
+    }
+
+    /**
+     * Records the edit which reverts a change already made to the document
+     */
+    pub fn record(&mut self, inverse : Edit) {
+        self.redo_stack.clear();
+        match &mut self.open {
+            Some(transaction) => transaction.edits.push(inverse),
+            None => self.push_undo(Transaction{edits : vec![inverse]}),
+        }
+    }
+
+    /**
+     * Starts grouping edits, transactions can be nested and only the outermost one is recorded
+     */
+    pub fn begin_transaction(&mut self) {
+        if (self.depth == 0) {
+            self.open = Some(Transaction{edits : Vec::new()});
+        }
+        self.depth += 1;
+    }
+
+    pub fn end_transaction(&mut self) {
+        if (self.depth == 0) {
+            return;
+        }
+        self.depth -= 1;
+        if (self.depth == 0) {
+            if let Some(transaction) = self.open.take().filter(|t| !t.edits.is_empty()) {
+                self.push_undo(transaction);
+            }
+        }
+    }
+
+    pub fn set_limit(&mut self, limit : usize) {
+        self.limit = limit;
+        while (self.undo_stack.len() > self.limit) {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /**
+     * Reverts the last transaction, closing the open one first. Returns nodes whose geometry changed.
+     */
+    pub fn undo(&mut self, document : &mut Document) -> Option<Vec<NodeId>> {
+        while (self.depth > 0) {
+            self.end_transaction();
+        }
+        let transaction = self.undo_stack.pop_back()?;
+        let (redo, changed) = revert(document, transaction);
+        self.redo_stack.push(redo);
+        Some(changed)
+    }
+
+    /**
+     * Makes the last undone transaction again. Returns nodes whose geometry changed.
+     */
+    pub fn redo(&mut self, document : &mut Document) -> Option<Vec<NodeId>> {
+        let transaction = self.redo_stack.pop()?;
+        let (undo, changed) = revert(document, transaction);
+        self.push_undo(undo);
+        Some(changed)
+    }
+
+    fn push_undo(&mut self, transaction : Transaction) {
+        self.undo_stack.push_back(transaction);
+        while (self.undo_stack.len() > self.limit) {
+            self.undo_stack.pop_front();
+        }
+    }
+}
+
+/**
+ * Applies edits of the transaction from the last one, returns the transaction reverting that and the changed nodes
+ */
+fn revert(document : &mut Document, transaction : Transaction) -> (Transaction, Vec<NodeId>) {
+    let mut edits = Vec::with_capacity(transaction.edits.len());
+    let mut changed = Vec::new();
+    for edit in transaction.edits.into_iter().rev() {
+        changed.extend(edit.changed_geometry());
+        edits.extend(document.apply(edit));
+    }
+    (Transaction{edits}, changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{path::Path, renderer::{Brush, FillRule}};
+
+    use super::*;
+
+    fn square(document : &mut Document, parent : NodeId) -> NodeId {
+        let mut path = Path::new();
+        path.move_to(0.0, 0.0);
+        path.line_to(1.0, 0.0);
+        path.line_to(1.0, 1.0);
+        path.close();
+        document.add_shape(parent, Shape{path, fill : Some(Brush::Color(1.0, 0.0, 0.0, 1.0)), fill_rule : FillRule::NonZero, stroke : None}).unwrap()
+    }
+
+    fn name(document : &Document, id : NodeId) -> Option<&str> {
+        document.node(id).unwrap().name.as_deref()
+    }
+
+    fn rename(history : &mut History, document : &mut Document, id : NodeId, name : &str) {
+        assert!(history.apply(document, Edit::SetName(id, Some(name.to_string()))));
+    }
+
+    #[test]
+    fn transactions_are_undone_and_redone_in_order() {
+        let mut document = Document::new();
+        let root = document.root();
+        let id = square(&mut document, root);
+        let mut history = History::new(DEFAULT_HISTORY_LIMIT);
+
+        history.begin_transaction();
+        rename(&mut history, &mut document, id, "first");
+        rename(&mut history, &mut document, id, "second");
+        assert!(history.apply(&mut document, Edit::SetTransform(id, Matrix3x3::translation(1.0, 2.0))));
+        history.end_transaction();
+
+        // edits are reverted from the last one, so the name from before the transaction comes back
+        assert_eq!(history.undo(&mut document), Some(vec![id]));
+        assert_eq!(name(&document, id), None);
+        assert!(document.node(id).unwrap().transform.is_identity());
+        assert!(!history.can_undo());
+
+        assert_eq!(history.redo(&mut document), Some(vec![id]));
+        assert_eq!(name(&document, id), Some("second"));
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn nested_transactions_are_recorded_as_one() {
+        let mut document = Document::new();
+        let root = document.root();
+        let id = square(&mut document, root);
+        let mut history = History::new(DEFAULT_HISTORY_LIMIT);
+
+        history.begin_transaction();
+        history.begin_transaction();
+        rename(&mut history, &mut document, id, "inner");
+        history.end_transaction();
+        rename(&mut history, &mut document, id, "outer");
+        history.end_transaction();
+        // closing more transactions than were opened does nothing
+        history.end_transaction();
+
+        history.undo(&mut document);
+        assert_eq!(name(&document, id), None);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn undo_closes_the_open_transaction() {
+        let mut document = Document::new();
+        let root = document.root();
+        let id = square(&mut document, root);
+        let mut history = History::new(DEFAULT_HISTORY_LIMIT);
+
+        rename(&mut history, &mut document, id, "before");
+        history.begin_transaction();
+        rename(&mut history, &mut document, id, "open");
+        history.undo(&mut document);
+        assert_eq!(name(&document, id), Some("before"));
+
+        // later edits are transactions of their own again
+        rename(&mut history, &mut document, id, "after");
+        history.undo(&mut document);
+        assert_eq!(name(&document, id), Some("before"));
+    }
+
+    #[test]
+    fn new_edits_clear_redo() {
+        let mut document = Document::new();
+        let root = document.root();
+        let id = square(&mut document, root);
+        let mut history = History::new(DEFAULT_HISTORY_LIMIT);
+
+        rename(&mut history, &mut document, id, "first");
+        history.undo(&mut document);
+        assert!(history.can_redo());
+        rename(&mut history, &mut document, id, "second");
+        assert!(!history.can_redo());
+        assert_eq!(history.redo(&mut document), None);
+        assert_eq!(name(&document, id), Some("second"));
+    }
+
+    #[test]
+    fn limit_drops_the_oldest_transactions() {
+        let mut document = Document::new();
+        let root = document.root();
+        let id = square(&mut document, root);
+        let mut history = History::new(DEFAULT_HISTORY_LIMIT);
+
+        for name in ["first", "second", "third"] {
+            rename(&mut history, &mut document, id, name);
+        }
+        history.set_limit(2);
+        assert!(history.undo(&mut document).is_some());
+        assert!(history.undo(&mut document).is_some());
+        assert!(history.undo(&mut document).is_none());
+        assert_eq!(name(&document, id), Some("first"));
+
+        // the limit also applies to transactions recorded later
+        let mut history = History::new(1);
+        rename(&mut history, &mut document, id, "fourth");
+        rename(&mut history, &mut document, id, "fifth");
+        history.undo(&mut document);
+        assert!(!history.can_undo());
+        assert_eq!(name(&document, id), Some("fourth"));
+    }
+
+    #[test]
+    fn removed_nodes_come_back_with_their_ids() {
+        let mut document = Document::new();
+        let root = document.root();
+        let first = square(&mut document, root);
+        let group = document.add_group(root, Some("group".to_string())).unwrap();
+        let child = square(&mut document, group);
+        let last = square(&mut document, root);
+        let mut history = History::new(DEFAULT_HISTORY_LIMIT);
+
+        assert!(history.apply(&mut document, Edit::Remove(group)));
+        assert!(!document.contains(group) && !document.contains(child));
+        // nodes which are not in the document can't be edited
+        assert!(!history.apply(&mut document, Edit::Remove(child)));
+
+        history.undo(&mut document);
+        assert_eq!(document.node(child).unwrap().parent, Some(group));
+        assert_eq!(name(&document, group), Some("group"));
+        assert!(matches!(&document.node(root).unwrap().content, NodeContent::Group(children) if *children == vec![first, group, last]));
+
+        history.redo(&mut document);
+        assert!(!document.contains(group) && !document.contains(child));
+    }
+}
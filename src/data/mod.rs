@@ -1,19 +1,246 @@
-use std::clone;
+use std::collections::HashMap;
 
-use crate::renderer::{Brush, Polygon};
+use crate::{matrix::Matrix3x3, path::Path, renderer::{stroke::stroke_path, tesselation::{index_triangles, tesselate_path}, Brush, FillRule, Primitive, Stroke, StrokeStyle}};
 
+pub mod history;
+pub mod svg;
 
 
+/**
+ * Outline of a shape, the brush and the style it is drawn with
+ */
+#[derive(Clone)]
+pub struct ShapeStroke {
+    pub brush : Brush,
+    pub style : StrokeStyle,
+}
+
+/**
+ * Path filled and stroked with brushes, in the coordinates of its node
+ */
+#[derive(Clone)]
+pub struct Shape {
+    pub path : Path,
+    pub fill : Option<Brush>,
+    pub fill_rule : FillRule,
+    pub stroke : Option<ShapeStroke>,
+}
+
+impl Shape {
+    /**
+     * Tesselates the fill and the outline of the shape placed on the canvas by the transform.
+     * Stroke width is scaled by the average scale of the transform.
+     */
+    pub fn to_primitive(&self, transform : &Matrix3x3) -> Primitive {
+        if (!transform.is_identity()) {
+            let shape = Shape{
+                path : self.path.transformed(transform),
+                fill : self.fill.as_ref().map(|brush| brush.transformed(transform)),
+                fill_rule : self.fill_rule,
+                stroke : self.stroke.as_ref().map(|stroke| ShapeStroke{
+                    brush : stroke.brush.transformed(transform),
+                    style : stroke.style.scaled(f32::sqrt(transform.determinant().abs())),
+                }),
+            };
+            return shape.to_primitive(&Matrix3x3::identity());
+        }
+
+        let parts = match self.fill {
+            Some(_) => tesselate_path(&self.path, self.fill_rule),
+            None => Vec::new()
+        };
+
+        let stroke = self.stroke.as_ref().map(|stroke| {
+            let parts = stroke_path(&self.path, &stroke.style);
+            Stroke{mesh : index_triangles(&parts), parts, brush : stroke.brush.clone()}
+        });
+
+        Primitive{mesh : index_triangles(&parts), parts, fill : self.fill.clone(), fill_rule : self.fill_rule, stroke}
+    }
+}
+
+/**
+ * Identifier of a node, stays the same for the whole life of the document
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct NodeId(pub u32);
+
+pub enum NodeContent {
+    /** child nodes, drawn in order */
+    Group(Vec<NodeId>),
+    Shape(Shape),
+}
+
+pub struct Node {
+    pub id : NodeId,
+    pub name : Option<String>,
+    /** placement of the node in the coordinates of its parent */
+    pub transform : Matrix3x3,
+    pub visible : bool,
+    pub parent : Option<NodeId>,
+    pub content : NodeContent,
+}
+
+/**
+ * Node removed from the document together with its descendants, which can be put back where it was
+ */
+pub struct Subtree {
+    pub parent : NodeId,
+    /** position among the children of the parent */
+    pub index : usize,
+    pub nodes : Vec<Node>,
+}
+
+/**
+ * Tree of groups and shapes, starting with the root group
+ */
 pub struct Document {
-    
+    nodes : HashMap<NodeId, Node>,
+    root : NodeId,
+    next_id : u32,
 }
 
 impl Document {
     pub fn new() -> Document {
+        let root = NodeId(0);
+        let mut nodes = HashMap::new();
+        nodes.insert(root, Node{id : root, name : None, transform : Matrix3x3::identity(), visible : true, parent : None, content : NodeContent::Group(Vec::new())});
+
         Document{
-            
+            nodes,
+            root,
+            next_id : 1
         }
     }
 
-}
+    pub fn root(&self) -> NodeId {
+        self.root
+    }
+
+    pub fn node(&self, id : NodeId) -> Option<&Node> {
+        self.nodes.get(&id)
+    }
+
+    pub fn node_mut(&mut self, id : NodeId) -> Option<&mut Node> {
+        self.nodes.get_mut(&id)
+    }
+
+    pub fn contains(&self, id : NodeId) -> bool {
+        self.nodes.contains_key(&id)
+    }
+
+    pub fn add_group(&mut self, parent : NodeId, name : Option<String>) -> Option<NodeId> {
+        self.add_node(parent, name, NodeContent::Group(Vec::new()))
+    }
 
+    pub fn add_shape(&mut self, parent : NodeId, shape : Shape) -> Option<NodeId> {
+        self.add_node(parent, None, NodeContent::Shape(shape))
+    }
+
+    /**
+     * Appends node to the children of the parent, None if the parent is not a group in this document
+     */
+    fn add_node(&mut self, parent : NodeId, name : Option<String>, content : NodeContent) -> Option<NodeId> {
+        let id = NodeId(self.next_id);
+        match &mut self.nodes.get_mut(&parent)?.content {
+            NodeContent::Group(children) => children.push(id),
+            NodeContent::Shape(_) => return None
+        }
+        self.next_id += 1;
+        self.nodes.insert(id, Node{id, name, transform : Matrix3x3::identity(), visible : true, parent : Some(parent), content});
+        Some(id)
+    }
+
+    /**
+     * Removes node with all of its descendants, the root can't be removed
+     */
+    pub fn remove_node(&mut self, id : NodeId) -> Option<Subtree> {
+        let parent = self.nodes.get(&id)?.parent?;
+        let mut index = 0;
+        if let Some(NodeContent::Group(children)) = self.nodes.get_mut(&parent).map(|p| &mut p.content) {
+            index = children.iter().position(|child| *child == id)?;
+            children.remove(index);
+        }
+
+        let nodes = self.subtree(id).iter().filter_map(|id| self.nodes.remove(id)).collect();
+        Some(Subtree{parent, index, nodes})
+    }
+
+    /**
+     * Puts removed nodes back at their original place, fails if the parent is gone
+     */
+    pub fn restore_subtree(&mut self, subtree : Subtree) -> Result<(), Subtree> {
+        let Some(Node{content : NodeContent::Group(children), ..}) = self.nodes.get_mut(&subtree.parent) else {
+            return Err(subtree);
+        };
+        let Some(first) = subtree.nodes.first() else {
+            return Ok(());
+        };
+        children.insert(subtree.index.min(children.len()), first.id);
+        for node in subtree.nodes {
+            self.nodes.insert(node.id, node);
+        }
+        Ok(())
+    }
+
+    fn children<'a>(&self, node : &'a Node) -> &'a [NodeId] {
+        match &node.content {
+            NodeContent::Group(children) => children,
+            NodeContent::Shape(_) => &[]
+        }
+    }
+
+    /**
+     * Ids of the node and all of its descendants
+     */
+    pub fn subtree(&self, id : NodeId) -> Vec<NodeId> {
+        let mut ids = Vec::new();
+        let mut pending = vec![id];
+        while let Some(id) = pending.pop() {
+            if let Some(node) = self.nodes.get(&id) {
+                ids.push(id);
+                pending.extend(self.children(node).iter().rev());
+            }
+        }
+        ids
+    }
+
+    /**
+     * Transform from the coordinates of the node to the canvas, including its own transform
+     */
+    pub fn world_transform(&self, id : NodeId) -> Matrix3x3 {
+        let mut transform = Matrix3x3::identity();
+        let mut current = self.nodes.get(&id);
+        while let Some(node) = current {
+            transform = node.transform.multiply(&transform);
+            current = node.parent.and_then(|parent| self.nodes.get(&parent));
+        }
+        transform
+    }
+
+    /**
+     * Visible shapes in drawing order, with their transforms to the canvas
+     */
+    pub fn visible_shapes(&self) -> Vec<(NodeId, &Shape, Matrix3x3)> {
+        let mut shapes = Vec::new();
+        self.collect_visible_shapes(self.root, &Matrix3x3::identity(), &mut shapes);
+        shapes
+    }
+
+    fn collect_visible_shapes<'a>(&'a self, id : NodeId, parent_transform : &Matrix3x3, shapes : &mut Vec<(NodeId, &'a Shape, Matrix3x3)>) {
+        let Some(node) = self.nodes.get(&id) else {
+            return;
+        };
+        if (!node.visible) {
+            return;
+        }
+        let transform = parent_transform.multiply(&node.transform);
+        match &node.content {
+            NodeContent::Group(children) => for child in children {
+                self.collect_visible_shapes(*child, &transform, shapes);
+            },
+            NodeContent::Shape(shape) => shapes.push((id, shape, transform)),
+        }
+    }
+
+}
@@ -0,0 +1,390 @@
+use std::fmt::Write;
+
+use crate::{data::{Document, NodeContent, NodeId, Shape}, matrix::Matrix3x3, path::{Path, PathSegment}, renderer::{Brush, FillRule, Gradient, GradientStop, LineCap, LineJoin, Spread, StrokeStyle}};
+
+use super::color::format_color;
+
+/**
+ * Writes the shapes of the document as a standalone SVG file of the size of the canvas,
+ * on a rectangle painted with the background brush, which is left out when the background is transparent.
+ *
+ * Groups of the document are written as `g` elements and node names as ids, hidden nodes get `display="none"`.
+ * Shapes are wrapped in a group with the view transform, gradients follow the shapes painted with them.
+ * SVG has no conic gradients, shapes painted with them fall back to the average colour of the gradient over the full turn.
+ * Colours between gradient stops always change linearly in sRGB in SVG, so smooth gradients are written as linear ones
+ * and gradients mixed in other colour spaces as sRGB ones.
+ */
+pub fn export_svg(document : &Document, width : u32, height : u32, transform : &Matrix3x3, background : &Brush) -> String {
+    let mut exporter = Exporter{
+        document,
+        defs : String::new(),
+        body : String::new(),
+        gradients_count : 0,
+    };
+
+    let root = document.node(document.root()).unwrap();
+    let transform = transform.multiply(&root.transform);
+    if (root.visible) {
+        if let NodeContent::Group(children) = &root.content {
+            for child in children {
+                exporter.write_node(*child);
+            }
+        }
+    }
+
+    // background is placed on the canvas, so its gradients don't follow the view transform
+    let background = match background {
+        Brush::Color(_, _, _, a) if (*a <= 0.0) => None,
+        brush => Some(exporter.get_paint(brush)),
+    };
+
+    let mut svg = String::new();
+    writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#, width, height, width, height).unwrap();
+    if (!exporter.defs.is_empty()) {
+        writeln!(svg, "<defs>\n{}</defs>", exporter.defs).unwrap();
+    }
+    if let Some((paint, opacity)) = background {
+        writeln!(svg, r#"<rect width="100%" height="100%" fill="{}"{}/>"#, paint, opacity_attribute("fill-opacity", opacity)).unwrap();
+    }
+    if (transform.is_identity()) {
+        svg.push_str(&exporter.body);
+    } else {
+        writeln!(svg, r#"<g transform="{}">"#, format_matrix(&transform)).unwrap();
+        svg.push_str(&exporter.body);
+        svg.push_str("</g>\n");
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+struct Exporter<'a> {
+    document : &'a Document,
+    defs : String,
+    body : String,
+    gradients_count : usize,
+}
+
+impl<'a> Exporter<'a> {
+
+    /**
+     * Writes the node and its descendants
+     */
+    fn write_node(&mut self, id : NodeId) {
+        let Some(node) = self.document.node(id) else {
+            return;
+        };
+
+        let mut attributes = String::new();
+        if let Some(name) = &node.name {
+            write!(attributes, r#" id="{}""#, escape(name)).unwrap();
+        }
+        if (!node.transform.is_identity()) {
+            write!(attributes, r#" transform="{}""#, format_matrix(&node.transform)).unwrap();
+        }
+        if (!node.visible) {
+            attributes.push_str(r#" display="none""#);
+        }
+
+        match &node.content {
+            NodeContent::Group(children) => {
+                writeln!(self.body, "<g{}>", attributes).unwrap();
+                for child in children {
+                    self.write_node(*child);
+                }
+                self.body.push_str("</g>\n");
+            },
+            NodeContent::Shape(shape) => self.write_shape(shape, attributes),
+        }
+    }
+
+    fn write_shape(&mut self, shape : &Shape, mut attributes : String) {
+        match &shape.fill {
+            Some(brush) => {
+                let paint = self.get_paint(brush);
+                write!(attributes, r#" fill="{}"{}"#, paint.0, opacity_attribute("fill-opacity", paint.1)).unwrap();
+                if (shape.fill_rule == FillRule::EvenOdd) {
+                    attributes.push_str(r#" fill-rule="evenodd""#);
+                }
+            },
+            None => attributes.push_str(r#" fill="none""#)
+        }
+
+        if let Some(stroke) = &shape.stroke {
+            let paint = self.get_paint(&stroke.brush);
+            write!(attributes, r#" stroke="{}"{}"#, paint.0, opacity_attribute("stroke-opacity", paint.1)).unwrap();
+            write_stroke_style(&mut attributes, &stroke.style);
+        }
+
+        writeln!(self.body, r#"<path d="{}"{}/>"#, format_path_data(&shape.path), attributes).unwrap();
+    }
+
+    /**
+     * Value of the fill or stroke attribute and the opacity of solid colours
+     */
+    fn get_paint(&mut self, brush : &Brush) -> (String, f32) {
+        match brush {
+            Brush::Color(r, g, b, a) => (format_color(*r, *g, *b), *a),
+            Brush::LinearGradient(gradient) => {
+                let attributes = format!(r#"x1="{}" y1="{}" x2="{}" y2="{}""#, gradient.x1, gradient.y1, gradient.x2, gradient.y2);
+                (self.add_gradient("linearGradient", attributes, gradient), 1.0)
+            },
+            Brush::RadialGradient(gradient) => {
+                // end circle is the outer one, the focal circle around the start point is left out when it's a point in the centre
+                let mut attributes = format!(r#"cx="{}" cy="{}" r="{}""#, gradient.x2, gradient.y2, gradient.r2);
+                if (gradient.x1 != gradient.x2 || gradient.y1 != gradient.y2) {
+                    write!(attributes, r#" fx="{}" fy="{}""#, gradient.x1, gradient.y1).unwrap();
+                }
+                if (gradient.r1 != 0.0) {
+                    write!(attributes, r#" fr="{}""#, gradient.r1).unwrap();
+                }
+                (self.add_gradient("radialGradient", attributes, gradient), 1.0)
+            },
+            Brush::ConicGradient(gradient) => {
+                let [r, g, b, a] = get_average_color(gradient);
+                (format_color(r, g, b), a)
+            }
+        }
+    }
+
+    /**
+     * Adds gradient definition and returns reference to it
+     */
+    fn add_gradient(&mut self, element : &str, attributes : String, gradient : &Gradient) -> String {
+        self.gradients_count += 1;
+        let id = format!("gradient-{}", self.gradients_count);
+
+        let transform = gradient.transform.map(|t| format!(r#" gradientTransform="{}""#, format_matrix(&t))).unwrap_or_default();
+        let spread = match gradient.spread {
+            Spread::Pad => "",
+            Spread::Repeat => r#" spreadMethod="repeat""#,
+            Spread::Reflect => r#" spreadMethod="reflect""#,
+        };
+
+        writeln!(self.defs, r#"<{} id="{}" gradientUnits="userSpaceOnUse" {}{}{}>"#, element, id, attributes, transform, spread).unwrap();
+        for stop in &gradient.stops {
+            writeln!(self.defs, r#"<stop offset="{}" stop-color="{}"{}/>"#, stop.position, format_color(stop.r, stop.g, stop.b), opacity_attribute("stop-opacity", stop.a)).unwrap();
+        }
+        writeln!(self.defs, "</{}>", element).unwrap();
+
+        format!("url(#{})", id)
+    }
+}
+
+fn write_stroke_style(attributes : &mut String, style : &StrokeStyle) {
+    write!(attributes, r#" stroke-width="{}""#, style.width).unwrap();
+    match style.join {
+        LineJoin::Miter => (),
+        LineJoin::Round => attributes.push_str(r#" stroke-linejoin="round""#),
+        LineJoin::Bevel => attributes.push_str(r#" stroke-linejoin="bevel""#),
+    }
+    match style.cap {
+        LineCap::Butt => (),
+        LineCap::Round => attributes.push_str(r#" stroke-linecap="round""#),
+        LineCap::Square => attributes.push_str(r#" stroke-linecap="square""#),
+    }
+    if (style.join == LineJoin::Miter && style.miter_limit != 4.0) {
+        write!(attributes, r#" stroke-miterlimit="{}""#, style.miter_limit).unwrap();
+    }
+    if (style.is_dashed()) {
+        let dashes : Vec<String> = style.dash_array.iter().map(|d| d.to_string()).collect();
+        write!(attributes, r#" stroke-dasharray="{}""#, dashes.join(" ")).unwrap();
+        if (style.dash_offset != 0.0) {
+            write!(attributes, r#" stroke-dashoffset="{}""#, style.dash_offset).unwrap();
+        }
+    }
+}
+
+fn escape(text : &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn opacity_attribute(name : &str, opacity : f32) -> String {
+    if (opacity < 1.0) {
+        format!(r#" {}="{}""#, name, opacity)
+    } else {
+        String::new()
+    }
+}
+
+fn format_matrix(matrix : &Matrix3x3) -> String {
+    // adding zero turns negative zeros into positive ones
+    let [a, b, c, d, e, f] = matrix.affine().map(|v| v + 0.0);
+    format!("matrix({} {} {} {} {} {})", a, b, c, d, e, f)
+}
+
+/**
+ * Path data with absolute commands, arc rotation is written in degrees
+ */
+pub fn format_path_data(path : &Path) -> String {
+    let mut data = String::new();
+    for segment in path.segments() {
+        if (!data.is_empty()) {
+            data.push(' ');
+        }
+        match segment {
+            PathSegment::MoveTo(p) => write!(data, "M {} {}", p.x, p.y),
+            PathSegment::LineTo(p) => write!(data, "L {} {}", p.x, p.y),
+            PathSegment::QuadTo(cp, p) => write!(data, "Q {} {} {} {}", cp.x, cp.y, p.x, p.y),
+            PathSegment::CubicTo(c1, c2, p) => write!(data, "C {} {} {} {} {} {}", c1.x, c1.y, c2.x, c2.y, p.x, p.y),
+            PathSegment::ArcTo{rx, ry, rotation, large_arc, sweep, to} => write!(data, "A {} {} {} {} {} {} {}",
+                rx, ry, rotation.to_degrees(), *large_arc as u8, *sweep as u8, to.x, to.y),
+            PathSegment::Close => write!(data, "Z"),
+        }.unwrap();
+    }
+    data
+}
+
+/**
+ * Average colour of the gradient over positions from 0 to 1, colours before the first and after the last stop are extended.
+ * Colours between stops are blended symmetrically, so each span contributes the mean of its two stops.
+ */
+fn get_average_color(gradient : &Gradient) -> [f32; 4] {
+    let stops = &gradient.stops;
+    if (stops.is_empty()) {
+        return [0.0, 0.0, 0.0, 0.0];
+    }
+    let color = |s : &GradientStop| [s.r, s.g, s.b, s.a];
+
+    let first = &stops[0];
+    let last = &stops[stops.len() - 1];
+    let mut spans : Vec<(f32, [f32; 4])> = vec![
+        (first.position.clamp(0.0, 1.0), color(first)),
+        (1.0 - last.position.clamp(0.0, 1.0), color(last)),
+    ];
+    for pair in stops.windows(2) {
+        let length = pair[1].position.clamp(0.0, 1.0) - pair[0].position.clamp(0.0, 1.0);
+        let (a, b) = (color(&pair[0]), color(&pair[1]));
+        spans.push((length.max(0.0), [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0, (a[2] + b[2]) / 2.0, (a[3] + b[3]) / 2.0]));
+    }
+
+    let total : f32 = spans.iter().map(|(length, _)| length).sum();
+    if (total <= 0.0) {
+        return color(last);
+    }
+    let mut average = [0.0; 4];
+    for (length, color) in spans {
+        for i in 0..4 {
+            average[i] += color[i] * length / total;
+        }
+    }
+    average
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{data::ShapeStroke, point::Point, renderer::{ColorSpace, Interpolation}};
+
+    use super::{*, super::import::import_svg};
+
+    const TRANSPARENT : Brush = Brush::Color(0.0, 0.0, 0.0, 0.0);
+
+    fn gradient(stops : [(f32, [f32; 4]); 2], transform : Option<Matrix3x3>) -> Gradient {
+        Gradient{
+            x1 : 0.0, y1 : 0.0, x2 : 10.0, y2 : 0.0, r1 : 0.0, r2 : 0.0,
+            stops : stops.iter().map(|(position, [r, g, b, a])| GradientStop{position : *position, r : *r, g : *g, b : *b, a : *a}).collect(),
+            spread : Spread::Reflect,
+            interpolation : Interpolation::Linear,
+            color_space : ColorSpace::Srgb,
+            transform,
+        }
+    }
+
+    /** SVG of the document and a new document imported from it */
+    fn round_trip(document : &Document, background : &Brush) -> (String, Document) {
+        let svg = export_svg(document, 100, 100, &Matrix3x3::identity(), background);
+        let mut imported = Document::new();
+        let root = imported.root();
+        import_svg(&svg, &mut imported, root).unwrap();
+        (svg, imported)
+    }
+
+    fn placed(gradient : &Gradient, x : f32, y : f32) -> (f64, f64) {
+        let p = Point::new(x as f64, y as f64);
+        let p = gradient.transform.map_or(p, |transform| transform.transform_point(&p));
+        ((p.x * 1000.0).round() / 1000.0, (p.y * 1000.0).round() / 1000.0)
+    }
+
+    #[test]
+    fn exported_shapes_are_imported_back() {
+        let mut document = Document::new();
+        let root = document.root();
+
+        let mut path = Path::new();
+        path.move_to(1.0, 2.0);
+        path.line_to(10.0, 2.0);
+        path.quad_to(15.0, 5.0, 10.0, 10.0);
+        path.cubic_to(8.0, 12.0, 4.0, 12.0, 2.0, 10.0);
+        path.arc_to(10.0, 5.0, 30f64.to_radians(), true, false, 1.0, 2.0);
+        path.close();
+        let mut style = StrokeStyle::new(2.5);
+        style.join = LineJoin::Round;
+        style.cap = LineCap::Square;
+        style.dash_array = vec![4.0, 1.5];
+        style.dash_offset = 2.0;
+        let fill = gradient([(0.0, [1.0, 0.0, 0.0, 1.0]), (1.0, [0.0, 0.0, 1.0, 0.5])], Some(Matrix3x3::translation(5.0, 5.0).multiply(&Matrix3x3::scale(2.0, 1.0))));
+        let stroke = ShapeStroke{brush : Brush::Color(0.0, 1.0, 0.0, 0.5), style};
+        let first = document.add_shape(root, Shape{path : path.clone(), fill : Some(Brush::LinearGradient(fill.clone())), fill_rule : FillRule::EvenOdd, stroke : Some(stroke)}).unwrap();
+        document.node_mut(first).unwrap().name = Some(r#"<a & "b">"#.to_string());
+
+        // SVG has no conic gradients, so the shape is filled with their average colour
+        let conic = gradient([(0.0, [1.0, 0.0, 0.0, 0.5]), (1.0, [0.0, 0.0, 1.0, 0.5])], None);
+        let mut style = StrokeStyle::new(1.0);
+        style.miter_limit = 10.0;
+        let stroke = ShapeStroke{brush : Brush::Color(0.0, 0.0, 0.0, 1.0), style};
+        document.add_shape(root, Shape{path, fill : Some(Brush::ConicGradient(conic)), fill_rule : FillRule::NonZero, stroke : Some(stroke)}).unwrap();
+
+        let (svg, imported) = round_trip(&document, &TRANSPARENT);
+        assert!(!svg.contains("<rect"));
+        let shapes = imported.visible_shapes();
+        assert_eq!(shapes.len(), 2);
+        assert!(shapes.iter().all(|(_, _, transform)| transform.is_identity()));
+
+        let (id, shape, _) = shapes[0];
+        assert_eq!(imported.node(id).unwrap().name, document.node(first).unwrap().name);
+        assert_eq!(format_path_data(&shape.path), format_path_data(&document.visible_shapes()[0].1.path));
+        let Some(PathSegment::ArcTo{rotation, ..}) = shape.path.segments().get(4) else {
+            panic!("expected an arc");
+        };
+        assert!((rotation - 30f64.to_radians()).abs() < 1e-9);
+        assert!(shape.fill_rule == FillRule::EvenOdd);
+
+        let Some(Brush::LinearGradient(imported_fill)) = &shape.fill else {
+            panic!("expected a linear gradient");
+        };
+        assert_eq!(placed(imported_fill, imported_fill.x1, imported_fill.y1), placed(&fill, fill.x1, fill.y1));
+        assert_eq!(placed(imported_fill, imported_fill.x2, imported_fill.y2), placed(&fill, fill.x2, fill.y2));
+        assert!(imported_fill.spread == Spread::Reflect);
+        assert!(imported_fill.stops == fill.stops);
+
+        let stroke = shape.stroke.as_ref().unwrap();
+        assert!(stroke.brush == Brush::Color(0.0, 1.0, 0.0, 0.5));
+        assert_eq!(stroke.style.width, 2.5);
+        assert!(stroke.style.join == LineJoin::Round && stroke.style.cap == LineCap::Square);
+        assert_eq!((stroke.style.dash_array.clone(), stroke.style.dash_offset), (vec![4.0, 1.5], 2.0));
+
+        let (_, shape, _) = shapes[1];
+        let Some(Brush::Color(r, g, b, a)) = shape.fill else {
+            panic!("expected a solid colour");
+        };
+        let purple = 128.0 / 255.0;
+        assert!((r - purple).abs() < 1e-6 && g == 0.0 && (b - purple).abs() < 1e-6 && a == 0.5);
+        let style = &shape.stroke.as_ref().unwrap().style;
+        assert!(style.join == LineJoin::Miter && style.miter_limit == 10.0 && !style.is_dashed());
+    }
+
+    #[test]
+    fn background() {
+        let document = Document::new();
+        let (svg, imported) = round_trip(&document, &TRANSPARENT);
+        assert!(!svg.contains("<rect"));
+        assert!(imported.visible_shapes().is_empty());
+
+        // the background is imported as a shape covering the canvas
+        let (_, imported) = round_trip(&document, &Brush::Color(0.0, 0.0, 1.0, 0.5));
+        let shapes = imported.visible_shapes();
+        assert_eq!(shapes.len(), 1);
+        assert!(shapes[0].1.fill == Some(Brush::Color(0.0, 0.0, 1.0, 0.5)));
+        let bounds = shapes[0].1.path.bounds().unwrap();
+        assert_eq!((bounds.left(), bounds.top(), bounds.width(), bounds.height()), (0.0, 0.0, 100.0, 100.0));
+    }
+}
@@ -0,0 +1,797 @@
+use std::{collections::HashMap, fmt::Display};
+
+use roxmltree::Node;
+
+use crate::{bounds::Bounds, data::{Document, NodeId, Shape, ShapeStroke}, matrix::Matrix3x3, path::Path, point::Point, renderer::{Brush, ColorSpace, FillRule, Gradient, GradientStop, Interpolation, LineCap, LineJoin, Spread, StrokeStyle}};
+
+use super::{color::parse_color, path_data::parse_path_data_partially};
+
+const XLINK_NAMESPACE : &str = "http://www.w3.org/1999/xlink";
+
+/**
+ * Longest chain of gradients referencing each other through `href`, protects against cycles
+ */
+const MAX_GRADIENT_REFERENCES : usize = 16;
+
+#[derive(Debug)]
+pub struct SvgError {
+    pub message : String,
+}
+
+impl Display for SvgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/**
+ * Imports an SVG document into a new group of the parent node and returns the group.
+ * Paths, rects, circles, ellipses, lines, polygons and polylines become shapes, and `g` elements become groups,
+ * each node keeps the transform of its element and the element's id as its name.
+ * Fills and strokes are solid colours or linear and radial gradients.
+ *
+ * Group opacity is applied to every shape of the group separately.
+ * Nothing is added to the document when the import fails.
+ */
+pub fn import_svg(source : &str, document : &mut Document, parent : NodeId) -> Result<NodeId, SvgError> {
+    let xml = roxmltree::Document::parse(source).map_err(|e| SvgError{message : format!("Invalid SVG document: {}", e)})?;
+    let root = xml.root_element();
+    if (root.tag_name().name() != "svg") {
+        return Err(SvgError{message : format!("Expected svg root element, found {}", root.tag_name().name())});
+    }
+
+    let gradients = xml.descendants()
+        .filter(|node| matches!(node.tag_name().name(), "linearGradient" | "radialGradient"))
+        .filter_map(|node| node.attribute("id").map(|id| (id, node)))
+        .collect();
+
+    let group = document.add_group(parent, root.attribute("id").map(str::to_string))
+        .ok_or_else(|| SvgError{message : "SVG can only be imported into a group".to_string()})?;
+    document.node_mut(group).unwrap().transform = get_viewbox_transform(&root);
+
+    let mut importer = Importer{gradients, viewport : get_viewport_size(&root), document};
+    let style = Style::of_element(&root, &Style::new());
+    if let Err(error) = importer.import_children(&root, &style, group) {
+        importer.document.remove_node(group);
+        return Err(error);
+    }
+
+    Ok(group)
+}
+
+struct Importer<'a, 'input, 'd> {
+    gradients : HashMap<&'a str, Node<'a, 'input>>,
+    /** size of the viewport, which percentages of user space lengths refer to */
+    viewport : (f64, f64),
+    document : &'d mut Document,
+}
+
+#[derive(Clone)]
+enum Paint {
+    None,
+    Color([f32; 4]),
+    /** gradient referenced by its id */
+    Url(String),
+}
+
+/**
+ * Inherited presentation properties
+ */
+#[derive(Clone)]
+struct Style {
+    color : [f32; 4],
+    fill : Paint,
+    fill_opacity : f32,
+    fill_rule : FillRule,
+    stroke : Paint,
+    stroke_opacity : f32,
+    stroke_style : StrokeStyle,
+    opacity : f32,
+}
+
+impl Style {
+    /**
+     * Initial values of the properties
+     */
+    fn new() -> Style {
+        Style{
+            color : [0.0, 0.0, 0.0, 1.0],
+            fill : Paint::Color([0.0, 0.0, 0.0, 1.0]),
+            fill_opacity : 1.0,
+            fill_rule : FillRule::NonZero,
+            stroke : Paint::None,
+            stroke_opacity : 1.0,
+            stroke_style : StrokeStyle::new(1.0),
+            opacity : 1.0,
+        }
+    }
+
+    /**
+     * Style of the element, properties in the `style` attribute take precedence over presentation attributes.
+     * Invalid values are ignored, so that the inherited ones stay in use.
+     */
+    fn of_element(node : &Node, parent : &Style) -> Style {
+        let mut style = parent.clone();
+        style.opacity = 1.0;
+
+        let mut properties : Vec<(&str, &str)> = node.attributes().filter(|a| a.namespace().is_none()).map(|a| (a.name(), a.value())).collect();
+        if let Some(declarations) = node.attribute("style") {
+            properties.extend(declarations.split(';').filter_map(|declaration| {
+                let (name, value) = declaration.split_once(':')?;
+                Some((name.trim(), value.trim()))
+            }));
+        }
+
+        // colour has to be known before it is used as currentColor
+        properties.sort_by_key(|(name, _)| *name != "color");
+
+        for (name, value) in properties {
+            let value = value.trim();
+            if (value == "inherit") {
+                continue;
+            }
+            style.apply_property(name, value);
+        }
+
+        // opacity is not inherited, but the children are drawn with the opacity of the group
+        style.opacity *= parent.opacity;
+        style
+    }
+
+    fn apply_property(&mut self, name : &str, value : &str) {
+        match name {
+            "color" => if let Some(color) = parse_color(value) {
+                self.color = color;
+            },
+            "fill" => if let Some(paint) = parse_paint(value, &self.color) {
+                self.fill = paint;
+            },
+            "stroke" => if let Some(paint) = parse_paint(value, &self.color) {
+                self.stroke = paint;
+            },
+            "fill-opacity" => if let Some(opacity) = parse_opacity(value) {
+                self.fill_opacity = opacity;
+            },
+            "stroke-opacity" => if let Some(opacity) = parse_opacity(value) {
+                self.stroke_opacity = opacity;
+            },
+            "opacity" => if let Some(opacity) = parse_opacity(value) {
+                self.opacity = opacity;
+            },
+            "fill-rule" => if let Some(fill_rule) = FillRule::from_name(value) {
+                self.fill_rule = fill_rule;
+            },
+            "stroke-width" => if let Some(width) = parse_length(value).filter(|w| *w >= 0.0) {
+                self.stroke_style.width = width as f32;
+            },
+            "stroke-linejoin" => if let Some(join) = LineJoin::from_name(value) {
+                self.stroke_style.join = join;
+            },
+            "stroke-linecap" => if let Some(cap) = LineCap::from_name(value) {
+                self.stroke_style.cap = cap;
+            },
+            "stroke-miterlimit" => if let Some(limit) = value.parse::<f32>().ok().filter(|l| *l >= 1.0) {
+                self.stroke_style.miter_limit = limit;
+            },
+            "stroke-dasharray" => {
+                if (value == "none") {
+                    self.stroke_style.dash_array = Vec::new();
+                } else if let Some(dashes) = parse_list(value).into_iter().map(|d| parse_length(d).map(|d| d as f32)).collect() {
+                    self.stroke_style.dash_array = dashes;
+                }
+            },
+            "stroke-dashoffset" => if let Some(offset) = parse_length(value) {
+                self.stroke_style.dash_offset = offset as f32;
+            },
+            _ => ()
+        }
+    }
+}
+
+impl<'a, 'input, 'd> Importer<'a, 'input, 'd> {
+
+    fn import_children(&mut self, node : &Node, style : &Style, parent : NodeId) -> Result<(), SvgError> {
+        for child in node.children().filter(|child| child.is_element()) {
+            self.import_element(&child, style, parent)?;
+        }
+        Ok(())
+    }
+
+    fn import_element(&mut self, node : &Node, parent_style : &Style, parent : NodeId) -> Result<(), SvgError> {
+        if (node.attribute("display") == Some("none")) {
+            return Ok(());
+        }
+
+        let style = Style::of_element(node, parent_style);
+        // invalid transforms are ignored like other invalid attributes
+        let transform = node.attribute("transform").and_then(|value| parse_transform(value).ok()).unwrap_or(Matrix3x3::identity());
+
+        let path = match node.tag_name().name() {
+            "g" | "svg" | "a" => {
+                let group = self.document.add_group(parent, None).unwrap();
+                self.set_node_properties(group, node, &transform);
+                return self.import_children(node, &style, group);
+            },
+            // like in browsers paths are drawn up to the first error in their data
+            "path" => node.attribute("d").map(|d| parse_path_data_partially(d).0),
+            "rect" => self.get_rect_path(node),
+            "circle" => {
+                let r = self.get_length(node, "r", Axis::Diagonal);
+                self.get_ellipse_path(node, r, r)
+            },
+            "ellipse" => {
+                let rx = self.get_length(node, "rx", Axis::X);
+                let ry = self.get_length(node, "ry", Axis::Y);
+                self.get_ellipse_path(node, rx, ry)
+            },
+            "line" => {
+                let mut path = Path::new();
+                path.move_to(self.get_length(node, "x1", Axis::X), self.get_length(node, "y1", Axis::Y));
+                path.line_to(self.get_length(node, "x2", Axis::X), self.get_length(node, "y2", Axis::Y));
+                Some(path)
+            },
+            name @ ("polygon" | "polyline") => match node.attribute("points").filter(|points| !points.trim().is_empty()) {
+                // list of points has the same syntax as the coordinates of a move followed by implicit lines
+                Some(points) => {
+                    let (mut path, _) = parse_path_data_partially(&format!("M {}", points));
+                    if (name == "polygon" && !path.segments().is_empty()) {
+                        path.close();
+                    }
+                    Some(path)
+                },
+                None => None
+            },
+            // definitions and unsupported elements are not drawn
+            _ => None
+        };
+
+        if let Some(shape) = path.and_then(|path| self.get_shape(path, &style)) {
+            let id = self.document.add_shape(parent, shape).unwrap();
+            self.set_node_properties(id, node, &transform);
+        }
+        Ok(())
+    }
+
+    fn set_node_properties(&mut self, id : NodeId, element : &Node, transform : &Matrix3x3) {
+        let node = self.document.node_mut(id).unwrap();
+        node.name = element.attribute("id").map(str::to_string);
+        node.transform = *transform;
+    }
+
+    /**
+     * Shape of the path painted with the style, None if it is neither filled nor stroked
+     */
+    fn get_shape(&self, path : Path, style : &Style) -> Option<Shape> {
+        let bounds = path.bounds();
+
+        let fill = self.get_brush(&style.fill, style.fill_opacity * style.opacity, &bounds);
+        let stroke = self.get_brush(&style.stroke, style.stroke_opacity * style.opacity, &bounds)
+            .filter(|_| style.stroke_style.width > 0.0)
+            .map(|brush| ShapeStroke{brush, style : style.stroke_style.clone()});
+
+        if (fill.is_none() && stroke.is_none()) {
+            return None;
+        }
+        Some(Shape{path, fill, fill_rule : style.fill_rule, stroke})
+    }
+
+    fn get_rect_path(&self, node : &Node) -> Option<Path> {
+        let x = self.get_length(node, "x", Axis::X);
+        let y = self.get_length(node, "y", Axis::Y);
+        let width = self.get_length(node, "width", Axis::X);
+        let height = self.get_length(node, "height", Axis::Y);
+        if (width <= 0.0 || height <= 0.0) {
+            return None;
+        }
+
+        // missing radius is the same as the other one, and both are at most half of the side
+        let rx = node.attribute("rx").map(|_| self.get_length(node, "rx", Axis::X));
+        let ry = node.attribute("ry").map(|_| self.get_length(node, "ry", Axis::Y));
+        let (rx, ry) = match (rx, ry) {
+            (Some(rx), Some(ry)) => (rx, ry),
+            (Some(r), None) | (None, Some(r)) => (r, r),
+            (None, None) => (0.0, 0.0),
+        };
+        let rx = rx.clamp(0.0, width / 2.0);
+        let ry = ry.clamp(0.0, height / 2.0);
+
+        let mut path = Path::new();
+        if (rx > 0.0 && ry > 0.0) {
+            path.move_to(x + rx, y);
+            path.line_to(x + width - rx, y);
+            path.arc_to(rx, ry, 0.0, false, true, x + width, y + ry);
+            path.line_to(x + width, y + height - ry);
+            path.arc_to(rx, ry, 0.0, false, true, x + width - rx, y + height);
+            path.line_to(x + rx, y + height);
+            path.arc_to(rx, ry, 0.0, false, true, x, y + height - ry);
+            path.line_to(x, y + ry);
+            path.arc_to(rx, ry, 0.0, false, true, x + rx, y);
+        } else {
+            path.move_to(x, y);
+            path.line_to(x + width, y);
+            path.line_to(x + width, y + height);
+            path.line_to(x, y + height);
+        }
+        path.close();
+        Some(path)
+    }
+
+    fn get_ellipse_path(&self, node : &Node, rx : f64, ry : f64) -> Option<Path> {
+        if (rx <= 0.0 || ry <= 0.0) {
+            return None;
+        }
+        let cx = self.get_length(node, "cx", Axis::X);
+        let cy = self.get_length(node, "cy", Axis::Y);
+
+        let mut path = Path::new();
+        path.move_to(cx + rx, cy);
+        path.arc_to(rx, ry, 0.0, false, true, cx - rx, cy);
+        path.arc_to(rx, ry, 0.0, false, true, cx + rx, cy);
+        path.close();
+        Some(path)
+    }
+
+    /**
+     * Length attribute in user space, percentages refer to the viewport, missing or invalid values are 0
+     */
+    fn get_length(&self, node : &Node, name : &str, axis : Axis) -> f64 {
+        node.attribute(name).and_then(|value| parse_coordinate(value, axis.reference(self.viewport))).unwrap_or(0.0)
+    }
+
+    fn get_brush(&self, paint : &Paint, opacity : f32, bounds : &Option<Bounds>) -> Option<Brush> {
+        match paint {
+            Paint::None => None,
+            Paint::Color([r, g, b, a]) => Some(Brush::Color(*r, *g, *b, a * opacity)),
+            Paint::Url(id) => {
+                let node = self.gradients.get(id.as_str())?;
+                self.get_gradient_brush(node, opacity, bounds)
+            }
+        }
+    }
+
+    /**
+     * Maps gradient onto a brush in the coordinates of the shape. Attributes and stops missing on the gradient
+     * are taken from the gradients it references through `href`.
+     */
+    fn get_gradient_brush(&self, node : &Node, opacity : f32, bounds : &Option<Bounds>) -> Option<Brush> {
+        let chain = self.get_gradient_chain(node);
+        let attribute = |name : &str| chain.iter().find_map(|node| node.attribute(name));
+
+        let stops = get_gradient_stops(&chain, opacity);
+        match stops.len() {
+            0 => return None,
+            // single stop paints the whole shape with its colour
+            1 => return Some(Brush::Color(stops[0].r, stops[0].g, stops[0].b, stops[0].a)),
+            _ => ()
+        }
+
+        let bounding_box_units = attribute("gradientUnits") != Some("userSpaceOnUse");
+        let units = if (bounding_box_units) {
+            // gradients relative to the bounding box of a shape with no width or height are not drawn
+            let bounds = bounds.as_ref().filter(|b| b.width() > 0.0 && b.height() > 0.0)?;
+            Matrix3x3::translation(bounds.left() as f32, bounds.top() as f32).multiply(&Matrix3x3::scale(bounds.width() as f32, bounds.height() as f32))
+        } else {
+            Matrix3x3::identity()
+        };
+        let gradient_transform = match attribute("gradientTransform") {
+            Some(value) => parse_transform(value).ok()?,
+            None => Matrix3x3::identity()
+        };
+        let matrix = units.multiply(&gradient_transform);
+        let spread = attribute("spreadMethod").and_then(Spread::from_name).unwrap_or(Spread::Pad);
+
+        // fractions of the bounding box, or percentages of the viewport
+        let coordinate = |name : &str, axis : Axis, default : f64| {
+            let reference = if (bounding_box_units) {1.0} else {axis.reference(self.viewport)};
+            attribute(name).and_then(|value| parse_coordinate(value, reference)).unwrap_or(default * reference)
+        };
+
+        if (node.tag_name().name() == "linearGradient") {
+            let start = Point::new(coordinate("x1", Axis::X, 0.0), coordinate("y1", Axis::Y, 0.0));
+            let end = Point::new(coordinate("x2", Axis::X, 1.0), coordinate("y2", Axis::Y, 0.0));
+
+            let gradient = Gradient{x1 : start.x as f32, y1 : start.y as f32, x2 : end.x as f32, y2 : end.y as f32, r1 : 0.0, r2 : 0.0, stops, spread, interpolation : Interpolation::Linear, color_space : ColorSpace::Srgb, transform : None};
+            Some(Brush::LinearGradient(gradient).transformed(&matrix))
+        } else {
+            let center = Point::new(coordinate("cx", Axis::X, 0.5), coordinate("cy", Axis::Y, 0.5));
+            let radius = coordinate("r", Axis::Diagonal, 0.5);
+            if (radius <= 0.0) {
+                // colour of the last stop is used for gradients with no radius
+                let last = &stops[stops.len() - 1];
+                return Some(Brush::Color(last.r, last.g, last.b, last.a));
+            }
+
+            // focal circle, from which the gradient starts, is a point in the centre unless given
+            let focus = Point::new(
+                if (attribute("fx").is_some()) {coordinate("fx", Axis::X, 0.0)} else {center.x},
+                if (attribute("fy").is_some()) {coordinate("fy", Axis::Y, 0.0)} else {center.y},
+            );
+            let focal_radius = coordinate("fr", Axis::Diagonal, 0.0);
+
+            let gradient = Gradient{
+                x1 : focus.x as f32, y1 : focus.y as f32, r1 : focal_radius as f32,
+                x2 : center.x as f32, y2 : center.y as f32, r2 : radius as f32,
+                stops, spread, interpolation : Interpolation::Linear, color_space : ColorSpace::Srgb, transform : None};
+            Some(Brush::RadialGradient(gradient).transformed(&matrix))
+        }
+    }
+
+    /**
+     * Gradient followed by the gradients it references
+     */
+    fn get_gradient_chain(&self, node : &Node<'a, 'input>) -> Vec<Node<'a, 'input>> {
+        let mut chain = vec![*node];
+        while (chain.len() < MAX_GRADIENT_REFERENCES) {
+            let last = chain[chain.len() - 1];
+            let reference = last.attribute("href").or_else(|| last.attribute((XLINK_NAMESPACE, "href")));
+            match reference.and_then(|r| r.strip_prefix('#')).and_then(|id| self.gradients.get(id)) {
+                Some(next) => chain.push(*next),
+                None => break
+            }
+        }
+        chain
+    }
+}
+
+/**
+ * Stops of the first gradient in the chain that has any, with offsets kept increasing
+ */
+fn get_gradient_stops(chain : &Vec<Node>, opacity : f32) -> Vec<GradientStop> {
+    let Some(gradient) = chain.iter().find(|node| node.children().any(|child| child.has_tag_name("stop"))) else {
+        return Vec::new();
+    };
+
+    let mut stops : Vec<GradientStop> = Vec::new();
+    for stop in gradient.children().filter(|child| child.has_tag_name("stop")) {
+        let style = Style::of_element(&stop, &Style::new());
+        let property = |name : &str| {
+            let declared = stop.attribute("style").and_then(|declarations| declarations.split(';').find_map(|declaration| {
+                let (property, value) = declaration.split_once(':')?;
+                (property.trim() == name).then(|| value.trim())
+            }));
+            declared.or_else(|| stop.attribute(name))
+        };
+
+        let color = match property("stop-color") {
+            Some("currentColor") => style.color,
+            Some(value) => parse_color(value).unwrap_or([0.0, 0.0, 0.0, 1.0]),
+            None => [0.0, 0.0, 0.0, 1.0],
+        };
+        let stop_opacity = property("stop-opacity").and_then(parse_opacity).unwrap_or(1.0);
+
+        let previous = stops.last().map(|s| s.position).unwrap_or(0.0);
+        let offset = stop.attribute("offset").and_then(|value| parse_coordinate(value, 1.0)).unwrap_or(0.0) as f32;
+
+        stops.push(GradientStop{
+            position : offset.clamp(0.0, 1.0).max(previous),
+            r : color[0],
+            g : color[1],
+            b : color[2],
+            a : color[3] * stop_opacity * opacity,
+        });
+    }
+    stops
+}
+
+/**
+ * Direction of a length, which decides what its percentages refer to
+ */
+#[derive(Clone, Copy)]
+enum Axis {
+    X, Y, Diagonal
+}
+
+impl Axis {
+    fn reference(&self, (width, height) : (f64, f64)) -> f64 {
+        match self {
+            Axis::X => width,
+            Axis::Y => height,
+            Axis::Diagonal => f64::sqrt((width * width + height * height) / 2.0),
+        }
+    }
+}
+
+/**
+ * Number or percentage of the reference length
+ */
+fn parse_coordinate(value : &str, reference : f64) -> Option<f64> {
+    match value.trim().strip_suffix('%') {
+        Some(percentage) => percentage.trim().parse::<f64>().ok().map(|p| p / 100.0 * reference),
+        None => parse_length(value)
+    }
+}
+
+/**
+ * Length with an optional absolute unit, converted to pixels
+ */
+fn parse_length(value : &str) -> Option<f64> {
+    let value = value.trim();
+    let split = value.find(|c : char| c.is_ascii_alphabetic() && c != 'e' && c != 'E').unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let scale = match unit {
+        "" | "px" => 1.0,
+        "pt" => 4.0 / 3.0,
+        "pc" => 16.0,
+        "mm" => 96.0 / 25.4,
+        "cm" => 96.0 / 2.54,
+        "in" => 96.0,
+        _ => return None
+    };
+    number.parse::<f64>().ok().map(|n| n * scale)
+}
+
+fn parse_opacity(value : &str) -> Option<f32> {
+    parse_coordinate(value, 1.0).map(|o| (o as f32).clamp(0.0, 1.0))
+}
+
+fn parse_paint(value : &str, current_color : &[f32; 4]) -> Option<Paint> {
+    match value {
+        "none" => Some(Paint::None),
+        "currentColor" => Some(Paint::Color(*current_color)),
+        _ => match value.strip_prefix("url(") {
+            Some(reference) => {
+                let (reference, _fallback) = reference.split_once(')')?;
+                let id = reference.trim().trim_matches(|c| c == '"' || c == '\'').strip_prefix('#')?;
+                Some(Paint::Url(id.to_string()))
+            },
+            None => parse_color(value).map(Paint::Color)
+        }
+    }
+}
+
+/**
+ * Splits list of values separated by commas or whitespace
+ */
+fn parse_list(value : &str) -> Vec<&str> {
+    value.split(|c : char| c == ',' || c.is_whitespace()).filter(|v| !v.is_empty()).collect()
+}
+
+/**
+ * Parses the `transform` attribute, a list of matrix, translate, scale, rotate, skewX and skewY functions
+ * applied from the right to the left
+ */
+pub fn parse_transform(value : &str) -> Result<Matrix3x3, SvgError> {
+    let error = || SvgError{message : format!("Invalid transform {}", value)};
+
+    let mut result = Matrix3x3::identity();
+    let mut rest = value.trim();
+    while (!rest.is_empty()) {
+        let (name, after_name) = rest.split_once('(').ok_or_else(error)?;
+        let (arguments, after_arguments) = after_name.split_once(')').ok_or_else(error)?;
+        let arguments : Vec<f32> = parse_list(arguments).into_iter().map(|a| a.parse::<f32>()).collect::<Result<_, _>>().map_err(|_| error())?;
+
+        let transform = match (name.trim(), arguments.as_slice()) {
+            ("matrix", [a, b, c, d, e, f]) => Matrix3x3::from_affine(*a, *b, *c, *d, *e, *f),
+            ("translate", [x]) => Matrix3x3::translation(*x, 0.0),
+            ("translate", [x, y]) => Matrix3x3::translation(*x, *y),
+            ("scale", [s]) => Matrix3x3::scale(*s, *s),
+            ("scale", [x, y]) => Matrix3x3::scale(*x, *y),
+            ("rotate", [angle]) => Matrix3x3::rotation(angle.to_radians()),
+            ("rotate", [angle, x, y]) => Matrix3x3::translation(*x, *y)
+                .multiply(&Matrix3x3::rotation(angle.to_radians()))
+                .multiply(&Matrix3x3::translation(-x, -y)),
+            ("skewX", [angle]) => Matrix3x3::from_affine(1.0, 0.0, angle.to_radians().tan(), 1.0, 0.0, 0.0),
+            ("skewY", [angle]) => Matrix3x3::from_affine(1.0, angle.to_radians().tan(), 0.0, 1.0, 0.0, 0.0),
+            _ => return Err(error())
+        };
+        result = result.multiply(&transform);
+
+        rest = after_arguments.trim_start_matches(|c : char| c == ',' || c.is_whitespace());
+    }
+    Ok(result)
+}
+
+fn get_viewbox(root : &Node) -> Option<[f64; 4]> {
+    let values : Vec<f64> = parse_list(root.attribute("viewBox")?).into_iter().map(|v| v.parse::<f64>().ok()).collect::<Option<_>>()?;
+    match values.as_slice() {
+        [x, y, width, height] if (*width > 0.0 && *height > 0.0) => Some([*x, *y, *width, *height]),
+        _ => None
+    }
+}
+
+/**
+ * Size of the user space of the root element, the default size of a replaced element is used when it has none
+ */
+fn get_viewport_size(root : &Node) -> (f64, f64) {
+    if let Some([_, _, width, height]) = get_viewbox(root) {
+        return (width, height);
+    }
+    let width = root.attribute("width").and_then(parse_length).unwrap_or(300.0);
+    let height = root.attribute("height").and_then(parse_length).unwrap_or(150.0);
+    (width, height)
+}
+
+/**
+ * Maps the view box onto the width and height of the root element following `preserveAspectRatio`
+ */
+fn get_viewbox_transform(root : &Node) -> Matrix3x3 {
+    let Some([x, y, viewbox_width, viewbox_height]) = get_viewbox(root) else {
+        return Matrix3x3::identity();
+    };
+    let width = root.attribute("width").and_then(parse_length).unwrap_or(viewbox_width);
+    let height = root.attribute("height").and_then(parse_length).unwrap_or(viewbox_height);
+
+    let mut sx = width / viewbox_width;
+    let mut sy = height / viewbox_height;
+
+    let aspect_ratio = root.attribute("preserveAspectRatio").unwrap_or("xMidYMid meet");
+    let mut parts = aspect_ratio.split_whitespace();
+    let align = parts.next().unwrap_or("xMidYMid");
+    let slice = parts.next() == Some("slice");
+
+    let (mut tx, mut ty) = (-x * sx, -y * sy);
+    if (align != "none") {
+        let scale = if (slice) {f64::max(sx, sy)} else {f64::min(sx, sy)};
+        sx = scale;
+        sy = scale;
+        // fraction of the free space before the view box
+        let position = |name : &str| {
+            if (name.ends_with("Min")) {0.0} else if (name.ends_with("Max")) {1.0} else {0.5}
+        };
+        let (align_x, align_y) = align.split_at(align.find('Y').unwrap_or(align.len()));
+        tx = -x * scale + (width - viewbox_width * scale) * position(align_x);
+        ty = -y * scale + (height - viewbox_height * scale) * position(align_y);
+    }
+
+    Matrix3x3::from_affine(sx as f32, 0.0, 0.0, sy as f32, tx as f32, ty as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{data::NodeContent, path::PathSegment};
+
+    use super::*;
+
+    fn import(source : &str) -> Document {
+        let mut document = Document::new();
+        let root = document.root();
+        import_svg(source, &mut document, root).unwrap();
+        document
+    }
+
+    fn svg(content : &str) -> String {
+        format!("<svg xmlns='http://www.w3.org/2000/svg' width='100' height='100'>{}</svg>", content)
+    }
+
+    /** bounds of the flattened path, which are within its tolerance */
+    fn assert_bounds(shape : &Shape, [left, top, width, height] : [f64; 4]) {
+        let bounds = shape.path.bounds().unwrap();
+        let actual = [bounds.left(), bounds.top(), bounds.left() + bounds.width(), bounds.top() + bounds.height()];
+        let expected = [left, top, left + width, top + height];
+        assert!(actual.iter().zip(expected).all(|(a, e)| (a - e).abs() <= shape.path.tolerance()), "bounds {:?}", actual);
+    }
+
+    /** point of the gradient placed by its transform */
+    fn placed(gradient : &Gradient, x : f32, y : f32) -> (f64, f64) {
+        let p = Point::new(x as f64, y as f64);
+        let p = gradient.transform.map_or(p, |transform| transform.transform_point(&p));
+        ((p.x * 1000.0).round() / 1000.0, (p.y * 1000.0).round() / 1000.0)
+    }
+
+    fn fill_of(document : &Document) -> Option<Brush> {
+        document.visible_shapes()[0].1.fill.clone()
+    }
+
+    #[test]
+    fn basic_shapes() {
+        let document = import(&svg("
+            <rect id='rect' x='1' y='2' width='3' height='4'/>
+            <rect x='1' y='2' width='0' height='4'/>
+            <circle cx='10' cy='10' r='5'/>
+            <ellipse cx='10' cy='10' rx='5' ry='2' display='none'/>
+            <line x1='0' y1='0' x2='10' y2='5' stroke='red'/>
+            <polygon points='0,0 10,0 10,10'/>
+            <polyline points='0,0 10,0 10,10' fill='none' stroke='blue' stroke-width='2'/>
+            <g transform='translate(5 5)'><path d='M0 0 h10 v10 z'/></g>"));
+        let shapes = document.visible_shapes();
+        assert_eq!(shapes.len(), 6);
+
+        assert_eq!(document.node(shapes[0].0).unwrap().name.as_deref(), Some("rect"));
+        assert_bounds(shapes[0].1, [1.0, 2.0, 3.0, 4.0]);
+        assert_bounds(shapes[1].1, [5.0, 5.0, 10.0, 10.0]);
+        // a line has nothing to fill
+        assert!(matches!(shapes[2].1.stroke, Some(ShapeStroke{brush : Brush::Color(1.0, 0.0, 0.0, 1.0), ..})));
+        assert!(matches!(shapes[3].1.path.segments().last(), Some(PathSegment::Close)));
+        assert!(!matches!(shapes[4].1.path.segments().last(), Some(PathSegment::Close)));
+        assert!(shapes[4].1.fill.is_none() && shapes[4].1.stroke.as_ref().unwrap().style.width == 2.0);
+        let origin = shapes[5].2.transform_point(&Point::new(0.0, 0.0));
+        assert_eq!((origin.x, origin.y), (5.0, 5.0));
+    }
+
+    #[test]
+    fn gradient_units() {
+        let gradient = |attributes : &str| {
+            let document = import(&svg(&format!("
+                <defs><linearGradient id='g' {}><stop offset='0' stop-color='red'/><stop offset='1' stop-color='blue'/></linearGradient></defs>
+                <rect x='10' y='20' width='20' height='40' fill='url(#g)'/>", attributes)));
+            match fill_of(&document) {
+                Some(Brush::LinearGradient(gradient)) => gradient,
+                _ => panic!("expected a linear gradient"),
+            }
+        };
+
+        // fractions of the bounding box by default
+        let g = gradient("x1='0.5' y1='0.25'");
+        assert_eq!(placed(&g, g.x1, g.y1), (20.0, 30.0));
+        assert_eq!(placed(&g, g.x2, g.y2), (30.0, 20.0));
+
+        // user space coordinates, with percentages of the viewport
+        let g = gradient("gradientUnits='userSpaceOnUse' x1='5' y1='50%' x2='100%'");
+        assert_eq!(placed(&g, g.x1, g.y1), (5.0, 50.0));
+        assert_eq!(placed(&g, g.x2, g.y2), (100.0, 0.0));
+
+        // gradient transform is applied in the units of the gradient
+        let g = gradient("gradientUnits='userSpaceOnUse' x2='10' gradientTransform='translate(5 1) scale(2)'");
+        assert_eq!(placed(&g, g.x2, g.y2), (25.0, 1.0));
+        let g = gradient("gradientTransform='rotate(90)'");
+        assert_eq!(placed(&g, g.x2, g.y2), (10.0, 60.0));
+    }
+
+    #[test]
+    fn gradient_references() {
+        let document = import(&svg("
+            <linearGradient id='base' x2='0.5'><stop offset='0' stop-color='red'/><stop offset='1' stop-color='blue'/></linearGradient>
+            <linearGradient id='middle' href='#base' x1='0.25'/>
+            <linearGradient id='top' xmlns:xlink='http://www.w3.org/1999/xlink' xlink:href='#middle' spreadMethod='repeat'/>
+            <linearGradient id='cycle' href='#cycle'/>
+            <rect width='100' height='100' fill='url(#top)'/>
+            <rect width='100' height='100' fill='url(#cycle)' stroke='red'/>"));
+        let shapes = document.visible_shapes();
+        let Some(Brush::LinearGradient(gradient)) = &shapes[0].1.fill else {
+            panic!("expected a linear gradient");
+        };
+        // attributes and stops come from the closest gradient of the chain declaring them
+        assert_eq!(placed(gradient, gradient.x1, gradient.y1), (25.0, 0.0));
+        assert_eq!(placed(gradient, gradient.x2, gradient.y2), (50.0, 0.0));
+        assert!(gradient.spread == Spread::Repeat);
+        assert_eq!(gradient.stops.len(), 2);
+        // gradients referencing themselves have no stops and paint nothing
+        assert!(shapes[1].1.fill.is_none());
+    }
+
+    #[test]
+    fn focal_attributes() {
+        let radial = |attributes : &str| {
+            let document = import(&svg(&format!("
+                <radialGradient id='g' gradientUnits='userSpaceOnUse' cx='50' cy='50' r='40' {}>
+                    <stop offset='0' stop-color='white'/><stop offset='1' stop-color='black'/>
+                </radialGradient>
+                <rect width='100' height='100' fill='url(#g)'/>", attributes)));
+            match fill_of(&document) {
+                Some(Brush::RadialGradient(gradient)) => gradient,
+                _ => panic!("expected a radial gradient"),
+            }
+        };
+
+        // focal point is the centre unless given
+        let g = radial("");
+        assert_eq!((g.x1, g.y1, g.r1, g.x2, g.y2, g.r2), (50.0, 50.0, 0.0, 50.0, 50.0, 40.0));
+        let g = radial("fx='30'");
+        assert_eq!((g.x1, g.y1), (30.0, 50.0));
+        let g = radial("fx='30' fy='40' fr='5'");
+        assert_eq!((g.x1, g.y1, g.r1), (30.0, 40.0, 5.0));
+    }
+
+    #[test]
+    fn errors() {
+        let mut document = Document::new();
+        let root = document.root();
+        assert!(import_svg("<svg", &mut document, root).is_err());
+        assert!(import_svg("<html/>", &mut document, root).is_err());
+        // nothing is left behind by failed imports
+        assert!(matches!(&document.node(root).unwrap().content, NodeContent::Group(children) if children.is_empty()));
+
+        // paths are drawn up to their first error, invalid transforms are ignored
+        let document = import(&svg("
+            <path d='M0 0 L10 0 L' transform='scale(2'/>
+            <polygon points='0 0 10 0 10 10 5'/>
+            <linearGradient id='g' gradientTransform='skew(10)'><stop offset='0'/><stop offset='1'/></linearGradient>
+            <rect width='10' height='10' fill='url(#g)' stroke='red'/>"));
+        let shapes = document.visible_shapes();
+        assert_eq!(shapes[0].1.path.segments().len(), 2);
+        assert!(shapes[0].2.is_identity());
+        assert_eq!(shapes[1].1.path.segments().len(), 4);
+        // gradients with invalid transforms are not painted
+        assert!(shapes[2].1.fill.is_none());
+    }
+}
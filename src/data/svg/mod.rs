@@ -0,0 +1,4 @@
+pub mod color;
+pub mod export;
+pub mod import;
+pub mod path_data;
@@ -0,0 +1,313 @@
+use std::fmt::Display;
+
+use crate::{path::{Path, PathSegment}, point::Point};
+
+/**
+ * Error in SVG path data, with byte position in the input where it was found
+ */
+#[derive(Debug)]
+pub struct PathDataError {
+    pub position : usize,
+    pub message : String,
+}
+
+impl Display for PathDataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+/**
+ * Parses path data (the `d` attribute) of an SVG path, following the full SVG 1.1 grammar
+ */
+pub fn parse_path_data(data : &str) -> Result<Path, PathDataError> {
+    let mut path = Path::new();
+    PathDataParser{data : data.as_bytes(), position : 0}.parse(&mut path)?;
+    Ok(path)
+}
+
+/**
+ * Parses path data like `parse_path_data`, but keeps the segments before an error together with it,
+ * since SVG renders paths up to their first error
+ */
+pub fn parse_path_data_partially(data : &str) -> (Path, Option<PathDataError>) {
+    let mut path = Path::new();
+    let error = PathDataParser{data : data.as_bytes(), position : 0}.parse(&mut path).err();
+    (path, error)
+}
+
+struct PathDataParser<'a> {
+    data : &'a [u8],
+    position : usize,
+}
+
+impl<'a> PathDataParser<'a> {
+
+    /**
+     * Adds segments to the path, each one once all of its parameters are read
+     */
+    fn parse(&mut self, path : &mut Path) -> Result<(), PathDataError> {
+        let mut start = Point::new(0.0, 0.0);
+        let mut current = start;
+        // control point of the previous curve, reflected by the shorthand commands
+        let mut last_control : Option<(u8, Point)> = None;
+        let mut command : Option<u8> = None;
+
+        self.skip_whitespace();
+        while (self.position < self.data.len()) {
+            let c = self.data[self.position];
+            if (c.is_ascii_alphabetic()) {
+                if (command.is_none() && c != b'M' && c != b'm') {
+                    return Err(self.error("Path data has to start with a move command"));
+                }
+                self.position += 1;
+                command = Some(c);
+            } else {
+                // numbers without a command repeat the previous one, with moves followed by lines
+                command = match command {
+                    Some(b'M') => Some(b'L'),
+                    Some(b'm') => Some(b'l'),
+                    Some(b'Z') | Some(b'z') | None => return Err(self.error("Expected a command")),
+                    other => other,
+                };
+            }
+
+            let c = command.unwrap();
+            let relative = c.is_ascii_lowercase();
+            let origin = if (relative) {current} else {Point::new(0.0, 0.0)};
+
+            let mut control : Option<(u8, Point)> = None;
+            match c.to_ascii_uppercase() {
+                b'M' => {
+                    let p = origin + self.point()?;
+                    path.push(PathSegment::MoveTo(p));
+                    start = p;
+                    current = p;
+                },
+                b'L' => {
+                    current = origin + self.point()?;
+                    path.push(PathSegment::LineTo(current));
+                },
+                b'H' => {
+                    current = Point::new(origin.x + self.number()?, current.y);
+                    path.push(PathSegment::LineTo(current));
+                },
+                b'V' => {
+                    current = Point::new(current.x, origin.y + self.number()?);
+                    path.push(PathSegment::LineTo(current));
+                },
+                b'C' => {
+                    let c1 = origin + self.point()?;
+                    let c2 = origin + self.point()?;
+                    current = origin + self.point()?;
+                    path.push(PathSegment::CubicTo(c1, c2, current));
+                    control = Some((b'C', c2));
+                },
+                b'S' => {
+                    let c1 = reflect(&last_control, b'C', &current);
+                    let c2 = origin + self.point()?;
+                    current = origin + self.point()?;
+                    path.push(PathSegment::CubicTo(c1, c2, current));
+                    control = Some((b'C', c2));
+                },
+                b'Q' => {
+                    let cp = origin + self.point()?;
+                    current = origin + self.point()?;
+                    path.push(PathSegment::QuadTo(cp, current));
+                    control = Some((b'Q', cp));
+                },
+                b'T' => {
+                    let cp = reflect(&last_control, b'Q', &current);
+                    current = origin + self.point()?;
+                    path.push(PathSegment::QuadTo(cp, current));
+                    control = Some((b'Q', cp));
+                },
+                b'A' => {
+                    let rx = self.number()?;
+                    let ry = self.number()?;
+                    let rotation = self.number()?;
+                    let large_arc = self.flag()?;
+                    let sweep = self.flag()?;
+                    current = origin + self.point()?;
+                    path.push(PathSegment::ArcTo{rx, ry, rotation : rotation.to_radians(), large_arc, sweep, to : current});
+                },
+                b'Z' => {
+                    path.push(PathSegment::Close);
+                    current = start;
+                },
+                _ => return Err(PathDataError{position : self.position - 1, message : format!("Unknown command '{}'", c as char)}),
+            }
+            last_control = control;
+
+            self.skip_separators();
+        }
+
+        Ok(())
+    }
+
+    fn error(&self, message : &str) -> PathDataError {
+        PathDataError{position : self.position, message : message.to_string()}
+    }
+
+    fn skip_whitespace(&mut self) {
+        while (self.position < self.data.len() && self.data[self.position].is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    /**
+     * Skips whitespace with at most one comma
+     */
+    fn skip_separators(&mut self) {
+        self.skip_whitespace();
+        if (self.position < self.data.len() && self.data[self.position] == b',') {
+            self.position += 1;
+            self.skip_whitespace();
+        }
+    }
+
+    fn point(&mut self) -> Result<Point, PathDataError> {
+        let x = self.number()?;
+        let y = self.number()?;
+        Ok(Point::new(x, y))
+    }
+
+    fn number(&mut self) -> Result<f64, PathDataError> {
+        self.skip_separators();
+        let start = self.position;
+        let data = self.data;
+        let digits = |position : &mut usize| {
+            let from = *position;
+            while (*position < data.len() && data[*position].is_ascii_digit()) {
+                *position += 1;
+            }
+            *position > from
+        };
+
+        if (self.position < data.len() && (data[self.position] == b'+' || data[self.position] == b'-')) {
+            self.position += 1;
+        }
+        let mut has_digits = digits(&mut self.position);
+        if (self.position < data.len() && data[self.position] == b'.') {
+            self.position += 1;
+            has_digits |= digits(&mut self.position);
+        }
+        if (!has_digits) {
+            self.position = start;
+            return Err(self.error("Expected a number"));
+        }
+        if (self.position < data.len() && (data[self.position] == b'e' || data[self.position] == b'E')) {
+            let mantissa_end = self.position;
+            self.position += 1;
+            if (self.position < data.len() && (data[self.position] == b'+' || data[self.position] == b'-')) {
+                self.position += 1;
+            }
+            if (!digits(&mut self.position)) {
+                // not an exponent after all
+                self.position = mantissa_end;
+            }
+        }
+
+        // the slice consists of ascii characters only
+        std::str::from_utf8(&data[start..self.position]).unwrap()
+            .parse::<f64>()
+            .map_err(|_| PathDataError{position : start, message : "Invalid number".to_string()})
+    }
+
+    /**
+     * Arc flags are single characters, which don't have to be separated from what follows them
+     */
+    fn flag(&mut self) -> Result<bool, PathDataError> {
+        self.skip_separators();
+        match self.data.get(self.position) {
+            Some(b'0') => {self.position += 1; Ok(false)},
+            Some(b'1') => {self.position += 1; Ok(true)},
+            _ => Err(self.error("Expected an arc flag (0 or 1)")),
+        }
+    }
+}
+
+/**
+ * Control point for the shorthand curves, the previous one reflected around the current point
+ * if the previous command was a curve of the same kind, or the current point otherwise
+ */
+fn reflect(last_control : &Option<(u8, Point)>, kind : u8, current : &Point) -> Point {
+    match last_control {
+        Some((k, control)) if *k == kind => *current * 2.0 - *control,
+        _ => *current,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /** segments written back as command letters with absolute coordinates, arcs with their flags */
+    fn describe(path : &Path) -> Vec<String> {
+        path.segments().iter().map(|segment| match segment {
+            PathSegment::MoveTo(p) => format!("M {} {}", p.x, p.y),
+            PathSegment::LineTo(p) => format!("L {} {}", p.x, p.y),
+            PathSegment::QuadTo(c, p) => format!("Q {} {} {} {}", c.x, c.y, p.x, p.y),
+            PathSegment::CubicTo(c1, c2, p) => format!("C {} {} {} {} {} {}", c1.x, c1.y, c2.x, c2.y, p.x, p.y),
+            PathSegment::ArcTo{rx, ry, large_arc, sweep, to, ..} => format!("A {} {} {} {} {} {}", rx, ry, *large_arc as u8, *sweep as u8, to.x, to.y),
+            PathSegment::Close => "Z".to_string(),
+        }).collect()
+    }
+
+    fn parse(data : &str) -> Vec<String> {
+        describe(&parse_path_data(data).unwrap())
+    }
+
+    fn error_position(data : &str) -> usize {
+        parse_path_data(data).err().expect("path data should not parse").position
+    }
+
+    #[test]
+    fn absolute_and_relative_commands() {
+        assert_eq!(parse("M 10 20 L 30 40 l 5 5 H 50 v -10 h -5 V 0 z l 1 1"), vec![
+            "M 10 20", "L 30 40", "L 35 45", "L 50 45", "L 50 35", "L 45 35", "L 45 0", "Z", "L 11 21",
+        ]);
+        assert_eq!(parse("m 1 1 c 1 0 2 1 2 2 s 1 2 2 2 q 1 0 1 1 t 1 1"), vec![
+            "M 1 1", "C 2 1 3 2 3 3", "C 3 4 4 5 5 5", "Q 6 5 6 6", "Q 6 7 7 7",
+        ]);
+    }
+
+    #[test]
+    fn implicit_repeats() {
+        assert_eq!(parse("M0 0 10 0 10 10"), vec!["M 0 0", "L 10 0", "L 10 10"]);
+        assert_eq!(parse("m1 1 2 2 3 3"), vec!["M 1 1", "L 3 3", "L 6 6"]);
+        assert_eq!(parse("M0,0L1,1,2,2-3-3"), vec!["M 0 0", "L 1 1", "L 2 2", "L -3 -3"]);
+        assert_eq!(parse("M0 0h.5.5"), vec!["M 0 0", "L 0.5 0", "L 1 0"]);
+    }
+
+    #[test]
+    fn arc_flags() {
+        assert_eq!(parse("M0 0 a10 10 0 1 0 20 0"), vec!["M 0 0", "A 10 10 1 0 20 0"]);
+        // flags don't need separators
+        assert_eq!(parse("M0 0 A10 10 0 0120 0 10 10 30 1 1 40 0"), vec!["M 0 0", "A 10 10 0 1 20 0", "A 10 10 1 1 40 0"]);
+        assert_eq!(error_position("M0 0 a10 10 0 2 0 20 0"), 14);
+    }
+
+    #[test]
+    fn error_positions() {
+        assert_eq!(error_position("L 0 0"), 0);
+        assert_eq!(error_position("M 0 0 L 10"), 10);
+        assert_eq!(error_position("M 0 0 X 1 1"), 6);
+        assert_eq!(error_position("M 0 0 z 1 1"), 8);
+        assert_eq!(error_position("M 0 0 L 1 -"), 10);
+    }
+
+    #[test]
+    fn segments_before_an_error() {
+        let (path, error) = parse_path_data_partially("M0 0 L10 0 10 10 L");
+        assert_eq!(describe(&path), vec!["M 0 0", "L 10 0", "L 10 10"]);
+        assert_eq!(error.map(|e| e.position), Some(18));
+        // a segment missing some of its numbers is left out as a whole
+        let (path, error) = parse_path_data_partially("M0 0 C1 1 2 2");
+        assert_eq!(describe(&path), vec!["M 0 0"]);
+        assert!(error.is_some());
+        let (path, error) = parse_path_data_partially("M0 0 L1 1");
+        assert_eq!(describe(&path).len(), 2);
+        assert!(error.is_none());
+    }
+}
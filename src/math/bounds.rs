@@ -36,6 +36,22 @@ impl Bounds {
         }
     }
 
+    pub fn left(&self) -> f64 {
+        self.l
+    }
+
+    pub fn top(&self) -> f64 {
+        self.t
+    }
+
+    pub fn width(&self) -> f64 {
+        self.r - self.l
+    }
+
+    pub fn height(&self) -> f64 {
+        self.b - self.t
+    }
+
     pub fn to_rect(&self) -> Rect {
         Rect::new(self.l ,self.t, self.r - self.l,  self.b - self.t)
     }
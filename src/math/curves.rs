@@ -1,13 +1,19 @@
+use std::f64::consts::PI;
+
 use super::{bounds::Bounds, point::Point};
 
-struct QuadraticCurve {
-    a : Point,
-    cp : Point,
-    b : Point
+pub struct QuadraticCurve {
+    pub a : Point,
+    pub cp : Point,
+    pub b : Point
 }
 
 impl QuadraticCurve {
 
+    pub fn new(a : Point, cp : Point, b : Point) -> QuadraticCurve {
+        QuadraticCurve{a : a, cp : cp, b : b}
+    }
+
     /**
      * Returns x coord of the curve at specific t
      */
@@ -26,11 +32,26 @@ impl QuadraticCurve {
      * Get point on a curve
      * @param {Number} t position on a curve [0,1]
      */
-    fn get_p(&self, t : f64) -> Point {
+    pub fn get_p(&self, t : f64) -> Point {
         Point::new(self.get_x(t), self.get_y(t))
     }
 
-    fn get_bounds(&self) -> Bounds {
+    /**
+     * Appends points approximating the curve (without the starting point) to `points`,
+     * so that the polyline deviates from the curve by no more than `tolerance`
+     */
+    pub fn flatten(&self, tolerance : f64, points : &mut Vec<Point>) {
+        // deviation of n equal segments is bounded by |a - 2cp + b| / (4 n^2)
+        let dd = (self.a - self.cp * 2.0 + self.b).length();
+        let count = segments_count(f64::sqrt(dd / (4.0 * tolerance)));
+
+        for i in 1..count {
+            points.push(self.get_p(i as f64 / count as f64));
+        }
+        points.push(self.b);
+    }
+
+    pub fn get_bounds(&self) -> Bounds {
         // starting points
         let mut l;
         let mut r;
@@ -86,9 +107,174 @@ impl QuadraticCurve {
 
 
 
-struct BezierCurve {
-    a : Point,
-    c1 : Point,
-    c2 : Point,
-    b : Point
+pub struct BezierCurve {
+    pub a : Point,
+    pub c1 : Point,
+    pub c2 : Point,
+    pub b : Point
+}
+
+impl BezierCurve {
+
+    pub fn new(a : Point, c1 : Point, c2 : Point, b : Point) -> BezierCurve {
+        BezierCurve{a : a, c1 : c1, c2 : c2, b : b}
+    }
+
+    /**
+     * Returns x coord of the curve at specific t
+     */
+    pub fn get_x(&self, t : f64) -> f64 {
+        let mt = 1.0 - t;
+        self.a.x * mt * mt * mt + 3.0 * mt * mt * t * self.c1.x + 3.0 * mt * t * t * self.c2.x + t * t * t * self.b.x
+    }
+
+    /**
+     * Returns y coord of the curve at specific t
+     */
+    pub fn get_y(&self, t : f64) -> f64 {
+        let mt = 1.0 - t;
+        self.a.y * mt * mt * mt + 3.0 * mt * mt * t * self.c1.y + 3.0 * mt * t * t * self.c2.y + t * t * t * self.b.y
+    }
+
+    /**
+     * Get point on a curve
+     * @param {Number} t position on a curve [0,1]
+     */
+    pub fn get_p(&self, t : f64) -> Point {
+        Point::new(self.get_x(t), self.get_y(t))
+    }
+
+    /**
+     * Appends points approximating the curve (without the starting point) to `points`,
+     * so that the polyline deviates from the curve by no more than `tolerance`
+     */
+    pub fn flatten(&self, tolerance : f64, points : &mut Vec<Point>) {
+        // second derivative is bounded by 6 * max(|a - 2c1 + c2|, |c1 - 2c2 + b|),
+        // deviation of n equal segments by that divided by 8 n^2
+        let dd = f64::max((self.a - self.c1 * 2.0 + self.c2).length(), (self.c1 - self.c2 * 2.0 + self.b).length());
+        let count = segments_count(f64::sqrt(3.0 * dd / (4.0 * tolerance)));
+
+        for i in 1..count {
+            points.push(self.get_p(i as f64 / count as f64));
+        }
+        points.push(self.b);
+    }
+}
+
+/**
+ * Elliptical arc in center parametrization
+ */
+pub struct EllipticalArc {
+    pub center : Point,
+    pub rx : f64,
+    pub ry : f64,
+    /** rotation of the ellipse's x axis, in radians */
+    pub rotation : f64,
+    pub start_angle : f64,
+    /** negative for arcs going counter-clockwise (in screen coordinates) */
+    pub sweep_angle : f64,
+}
+
+impl EllipticalArc {
+
+    /**
+     * Converts arc given the SVG way, by its end points and flags, to the center parametrization.
+     * Returns None when the arc is a straight line (radius is 0 or end points are equal).
+     * Radii too small to reach the end point are scaled up, as in SVG.
+     */
+    pub fn from_endpoints(from : Point, rx : f64, ry : f64, rotation : f64, large_arc : bool, sweep : bool, to : Point) -> Option<EllipticalArc> {
+        let mut rx = rx.abs();
+        let mut ry = ry.abs();
+        if (rx == 0.0 || ry == 0.0 || from == to) {
+            return None;
+        }
+
+        let (sin, cos) = rotation.sin_cos();
+
+        // end points moved to the coordinate system of the ellipse
+        let dx = (from.x - to.x) / 2.0;
+        let dy = (from.y - to.y) / 2.0;
+        let x1 = cos * dx + sin * dy;
+        let y1 = -sin * dx + cos * dy;
+
+        let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+        if (lambda > 1.0) {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+
+        let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+        let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+        let mut factor = f64::sqrt(f64::max(0.0, numerator / denominator));
+        if (large_arc == sweep) {
+            factor = -factor;
+        }
+
+        let cx1 = factor * rx * y1 / ry;
+        let cy1 = -factor * ry * x1 / rx;
+
+        let center = Point::new(
+            cos * cx1 - sin * cy1 + (from.x + to.x) / 2.0,
+            sin * cx1 + cos * cy1 + (from.y + to.y) / 2.0,
+        );
+
+        let start_angle = f64::atan2((y1 - cy1) / ry, (x1 - cx1) / rx);
+        let end_angle = f64::atan2((-y1 - cy1) / ry, (-x1 - cx1) / rx);
+        let mut sweep_angle = end_angle - start_angle;
+
+        if (sweep && sweep_angle < 0.0) {
+            sweep_angle += 2.0 * PI;
+        } else if (!sweep && sweep_angle > 0.0) {
+            sweep_angle -= 2.0 * PI;
+        }
+
+        Some(EllipticalArc{center, rx, ry, rotation, start_angle, sweep_angle})
+    }
+
+    /**
+     * Get point on the arc at given angle of the ellipse
+     */
+    pub fn get_p_at_angle(&self, angle : f64) -> Point {
+        let (sin, cos) = self.rotation.sin_cos();
+        let x = self.rx * angle.cos();
+        let y = self.ry * angle.sin();
+        Point::new(self.center.x + cos * x - sin * y, self.center.y + sin * x + cos * y)
+    }
+
+    /**
+     * Get point on the arc
+     * @param {Number} t position on the arc [0,1]
+     */
+    pub fn get_p(&self, t : f64) -> Point {
+        self.get_p_at_angle(self.start_angle + self.sweep_angle * t)
+    }
+
+    /**
+     * Appends points approximating the arc (without the starting point) to `points`,
+     * so that the polyline deviates from the arc by no more than `tolerance`
+     */
+    pub fn flatten(&self, tolerance : f64, points : &mut Vec<Point>) {
+        let radius = f64::max(self.rx, self.ry);
+        // largest angle whose chord stays within tolerance from the arc
+        let step = if (tolerance < radius) {2.0 * f64::acos(1.0 - tolerance / radius)} else {PI / 2.0};
+        let count = segments_count(self.sweep_angle.abs() / step);
+
+        for i in 1..count {
+            points.push(self.get_p(i as f64 / count as f64));
+        }
+        points.push(self.get_p(1.0));
+    }
+}
+
+/**
+ * Upper limit of segments a single curve is split into
+ */
+const MAX_SEGMENTS : usize = 1024;
+
+fn segments_count(count : f64) -> usize {
+    if (count.is_nan()) {
+        1
+    } else {
+        (count.ceil() as usize).clamp(1, MAX_SEGMENTS)
+    }
 }
\ No newline at end of file
@@ -1,5 +1,7 @@
 
+use super::point::Point;
 
+#[derive(Clone, Copy, PartialEq)]
 pub struct Matrix3x3 {
     data : [f32; 9]
 }
@@ -28,4 +30,101 @@ impl Matrix3x3 {
             ]
         }
     }
+
+    /**
+     * Creates affine transform from the SVG `matrix(a b c d e f)` notation
+     */
+    pub fn from_affine(a : f32, b : f32, c : f32, d : f32, e : f32, f : f32) -> Matrix3x3 {
+        Matrix3x3::new(
+            a, c, e,
+            b, d, f,
+            0.0, 0.0, 1.0
+        )
+    }
+
+    pub fn translation(x : f32, y : f32) -> Matrix3x3 {
+        Matrix3x3::from_affine(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    pub fn scale(x : f32, y : f32) -> Matrix3x3 {
+        Matrix3x3::from_affine(x, 0.0, 0.0, y, 0.0, 0.0)
+    }
+
+    /**
+     * Rotation by angle in radians
+     */
+    pub fn rotation(angle : f32) -> Matrix3x3 {
+        let (sin, cos) = angle.sin_cos();
+        Matrix3x3::from_affine(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    /**
+     * Returns a, b, c, d, e, f coefficients of the affine part, as in SVG `matrix()`
+     */
+    pub fn affine(&self) -> [f32; 6] {
+        let m = &self.data;
+        [m[0], m[3], m[1], m[4], m[2], m[5]]
+    }
+
+    /**
+     * Matrix product, applying the result to a point is the same as applying `other` first and `self` after that
+     */
+    pub fn multiply(&self, other : &Matrix3x3) -> Matrix3x3 {
+        let a = &self.data;
+        let b = &other.data;
+        let mut data = [0.0; 9];
+        for row in 0..3 {
+            for column in 0..3 {
+                data[row * 3 + column] = a[row * 3] * b[column] + a[row * 3 + 1] * b[3 + column] + a[row * 3 + 2] * b[6 + column];
+            }
+        }
+        Matrix3x3{data}
+    }
+
+    pub fn transform_point(&self, p : &Point) -> Point {
+        let m = &self.data;
+        Point::new(
+            m[0] as f64 * p.x + m[1] as f64 * p.y + m[2] as f64,
+            m[3] as f64 * p.x + m[4] as f64 * p.y + m[5] as f64,
+        )
+    }
+
+    /**
+     * Transforms a vector, ignoring translation
+     */
+    pub fn transform_vector(&self, p : &Point) -> Point {
+        let m = &self.data;
+        Point::new(
+            m[0] as f64 * p.x + m[1] as f64 * p.y,
+            m[3] as f64 * p.x + m[4] as f64 * p.y,
+        )
+    }
+
+    /**
+     * Determinant of the linear part, negative for transforms that mirror
+     */
+    pub fn determinant(&self) -> f32 {
+        let m = &self.data;
+        m[0] * m[4] - m[1] * m[3]
+    }
+
+    /**
+     * Inverse of the affine transform, None if it collapses the plane into a line or a point
+     */
+    pub fn inverse(&self) -> Option<Matrix3x3> {
+        let determinant = self.determinant();
+        if (determinant == 0.0) {
+            return None;
+        }
+        let [a, b, c, d, e, f] = self.affine();
+        Some(Matrix3x3::from_affine(
+            d / determinant, -b / determinant,
+            -c / determinant, a / determinant,
+            (c * f - d * e) / determinant, (b * e - a * f) / determinant
+        ))
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.data == Matrix3x3::identity().data
+    }
 }
\ No newline at end of file
@@ -1,8 +1,6 @@
 use std::fmt::Display;
 use std::ops::{self, Bound};
 
-use wasm_bindgen::prelude::*;
-use wasm_bindgen::convert::ReturnWasmAbi;
 use num::Float;
 
 
@@ -12,6 +10,7 @@ pub mod line;
 pub mod point;
 pub mod curves;
 pub mod matrix;
+pub mod path;
 
 
 #[derive(Clone, Copy, PartialEq)]
@@ -0,0 +1,222 @@
+#[cfg(feature = "web")]
+use wasm_bindgen::prelude::*;
+
+use super::{bounds::Bounds, curves::{BezierCurve, EllipticalArc, QuadraticCurve}, matrix::Matrix3x3, point::Point};
+
+/**
+ * Default maximal distance between a curve and the polyline approximating it
+ */
+pub const DEFAULT_TOLERANCE : f64 = 0.25;
+
+#[derive(Clone, Copy)]
+pub enum PathSegment {
+    MoveTo(Point),
+    LineTo(Point),
+    /** control point, end point */
+    QuadTo(Point, Point),
+    /** first control point, second control point, end point */
+    CubicTo(Point, Point, Point),
+    /** elliptical arc given the SVG way, rotation is in radians */
+    ArcTo{rx : f64, ry : f64, rotation : f64, large_arc : bool, sweep : bool, to : Point},
+    Close,
+}
+
+/**
+ * Part of a flattened path, starting with a move
+ */
+pub struct Subpath {
+    pub points : Vec<Point>,
+    pub closed : bool,
+}
+
+/**
+ * Path made of subpaths consisting of lines, quadratic and cubic Bézier curves and elliptical arcs
+ */
+#[cfg_attr(feature = "web", wasm_bindgen)]
+#[derive(Clone)]
+pub struct Path {
+    segments : Vec<PathSegment>,
+    tolerance : f64,
+}
+
+#[cfg_attr(feature = "web", wasm_bindgen)]
+impl Path {
+
+    #[cfg_attr(feature = "web", wasm_bindgen(constructor))]
+    pub fn new() -> Path {
+        Path{segments : Vec::new(), tolerance : DEFAULT_TOLERANCE}
+    }
+
+    /**
+     * Sets maximal distance between curves and lines approximating them
+     */
+    pub fn set_tolerance(&mut self, tolerance : f64) {
+        self.tolerance = tolerance;
+    }
+
+    pub fn move_to(&mut self, x : f64, y : f64) {
+        self.segments.push(PathSegment::MoveTo(Point::new(x, y)));
+    }
+
+    pub fn line_to(&mut self, x : f64, y : f64) {
+        self.segments.push(PathSegment::LineTo(Point::new(x, y)));
+    }
+
+    pub fn quad_to(&mut self, cx : f64, cy : f64, x : f64, y : f64) {
+        self.segments.push(PathSegment::QuadTo(Point::new(cx, cy), Point::new(x, y)));
+    }
+
+    pub fn cubic_to(&mut self, c1x : f64, c1y : f64, c2x : f64, c2y : f64, x : f64, y : f64) {
+        self.segments.push(PathSegment::CubicTo(Point::new(c1x, c1y), Point::new(c2x, c2y), Point::new(x, y)));
+    }
+
+    /**
+     * Adds an elliptical arc to the point x, y, same as the SVG arc command, but with rotation in radians
+     */
+    pub fn arc_to(&mut self, rx : f64, ry : f64, rotation : f64, large_arc : bool, sweep : bool, x : f64, y : f64) {
+        self.segments.push(PathSegment::ArcTo{rx, ry, rotation, large_arc, sweep, to : Point::new(x, y)});
+    }
+
+    pub fn close(&mut self) {
+        self.segments.push(PathSegment::Close);
+    }
+}
+
+impl Path {
+
+    pub fn segments(&self) -> &Vec<PathSegment> {
+        &self.segments
+    }
+
+    pub fn tolerance(&self) -> f64 {
+        self.tolerance
+    }
+
+    pub fn push(&mut self, segment : PathSegment) {
+        self.segments.push(segment);
+    }
+
+    /**
+     * Bounds of the flattened path, None if it draws nothing
+     */
+    pub fn bounds(&self) -> Option<Bounds> {
+        let mut points = self.flatten().into_iter().flat_map(|subpath| subpath.points.into_iter());
+        let first = points.next()?;
+        Some(points.fold(Bounds::new_fast(first.x, first.y, first.x, first.y), |bounds, p| bounds.containing_point(&p)))
+    }
+
+    /**
+     * Returns copy of the path with all points transformed by the affine matrix.
+     * Arcs stay arcs, their radii and rotation are those of the transformed ellipse.
+     */
+    pub fn transformed(&self, matrix : &Matrix3x3) -> Path {
+        let t = |p : &Point| matrix.transform_point(p);
+        let mirrored = matrix.determinant() < 0.0;
+        let segments = self.segments.iter().map(|segment| match segment {
+            PathSegment::MoveTo(p) => PathSegment::MoveTo(t(p)),
+            PathSegment::LineTo(p) => PathSegment::LineTo(t(p)),
+            PathSegment::QuadTo(cp, p) => PathSegment::QuadTo(t(cp), t(p)),
+            PathSegment::CubicTo(c1, c2, p) => PathSegment::CubicTo(t(c1), t(c2), t(p)),
+            PathSegment::ArcTo{rx, ry, rotation, large_arc, sweep, to} => {
+                let (rx, ry, rotation) = transform_ellipse(matrix, *rx, *ry, *rotation);
+                PathSegment::ArcTo{rx, ry, rotation, large_arc : *large_arc, sweep : *sweep != mirrored, to : t(to)}
+            },
+            PathSegment::Close => PathSegment::Close,
+        }).collect();
+        Path{segments, tolerance : self.tolerance}
+    }
+
+    /**
+     * Approximates the path with polylines, one for each subpath.
+     * Drawing commands without a preceding move start at 0,0 or where the previous subpath was closed.
+     */
+    pub fn flatten(&self) -> Vec<Subpath> {
+        let mut subpaths : Vec<Subpath> = Vec::new();
+        let mut points : Vec<Point> = Vec::new();
+        let mut start = Point::new(0.0, 0.0);
+        let mut current = start;
+
+        for segment in self.segments.iter() {
+            if (points.is_empty()) {
+                if let PathSegment::MoveTo(_) | PathSegment::Close = segment {} else {
+                    points.push(current);
+                }
+            }
+
+            match *segment {
+                PathSegment::MoveTo(p) => {
+                    finish_subpath(&mut subpaths, &mut points, false);
+                    points.push(p);
+                    start = p;
+                    current = p;
+                },
+                PathSegment::LineTo(p) => {
+                    points.push(p);
+                    current = p;
+                },
+                PathSegment::QuadTo(cp, p) => {
+                    QuadraticCurve::new(current, cp, p).flatten(self.tolerance, &mut points);
+                    current = p;
+                },
+                PathSegment::CubicTo(c1, c2, p) => {
+                    BezierCurve::new(current, c1, c2, p).flatten(self.tolerance, &mut points);
+                    current = p;
+                },
+                PathSegment::ArcTo{rx, ry, rotation, large_arc, sweep, to} => {
+                    match EllipticalArc::from_endpoints(current, rx, ry, rotation, large_arc, sweep, to) {
+                        Some(arc) => {
+                            arc.flatten(self.tolerance, &mut points);
+                            // avoid rounding errors at the end point
+                            points.pop();
+                            points.push(to);
+                        },
+                        None => points.push(to)
+                    }
+                    current = to;
+                },
+                PathSegment::Close => {
+                    finish_subpath(&mut subpaths, &mut points, true);
+                    current = start;
+                }
+            }
+        }
+        finish_subpath(&mut subpaths, &mut points, false);
+
+        subpaths
+    }
+}
+
+fn finish_subpath(subpaths : &mut Vec<Subpath>, points : &mut Vec<Point>, closed : bool) {
+    // a lone move doesn't draw anything
+    if (points.len() > 1 || (closed && !points.is_empty())) {
+        let mut points = std::mem::take(points);
+        if (closed && points.len() > 1 && points[0] == points[points.len() - 1]) {
+            points.pop();
+        }
+        subpaths.push(Subpath{points, closed});
+    } else {
+        points.clear();
+    }
+}
+
+/**
+ * Radii and rotation of the ellipse transformed by the linear part of the matrix.
+ * The ellipse is the image of the unit circle under A = M * R * S, its axes follow from the eigen decomposition of A * At.
+ */
+fn transform_ellipse(matrix : &Matrix3x3, rx : f64, ry : f64, rotation : f64) -> (f64, f64, f64) {
+    let (sin, cos) = rotation.sin_cos();
+    let u = matrix.transform_vector(&Point::new(cos * rx, sin * rx));
+    let v = matrix.transform_vector(&Point::new(-sin * ry, cos * ry));
+
+    let p = u.x * u.x + v.x * v.x;
+    let q = u.x * u.y + v.x * v.y;
+    let r = u.y * u.y + v.y * v.y;
+
+    let mean = (p + r) / 2.0;
+    let deviation = f64::sqrt(((p - r) / 2.0).powi(2) + q * q);
+    let major = f64::sqrt(mean + deviation);
+    let minor = f64::sqrt(f64::max(0.0, mean - deviation));
+    let angle = 0.5 * f64::atan2(2.0 * q, p - r);
+
+    (major, minor, angle)
+}
@@ -0,0 +1,53 @@
+use crate::matrix::Matrix3x3;
+
+use super::{Brush, IndexedTriangles, Triangles};
+
+/**
+ * Target which primitives are drawn on. Triangles are filled with the brush set last
+ * and placed by the transform set last, gradients are given in the coordinates of the triangles and move with them.
+ * Triangles are composited over what was drawn before, pixels keep colours premultiplied by alpha.
+ */
+pub trait Backend {
+    /**
+     * Triangles kept by the backend, so that they're not sent again on every draw
+     */
+    type Mesh;
+
+    fn create_mesh(&mut self, triangles : &IndexedTriangles) -> Self::Mesh;
+
+    /**
+     * Replaces the triangles of the mesh, reusing its resources
+     */
+    fn update_mesh(&mut self, mesh : &mut Self::Mesh, triangles : &IndexedTriangles);
+
+    fn delete_mesh(&mut self, mesh : Self::Mesh);
+
+    /**
+     * Fills the triangles of the mesh with the current brush
+     */
+    fn draw_mesh(&mut self, mesh : &Self::Mesh);
+
+    /**
+     * Replaces all pixels of the target with the brush
+     */
+    fn clear(&mut self, brush : &Brush);
+
+    fn set_transform(&mut self, matrix : &Matrix3x3);
+
+    fn set_brush(&mut self, brush : &Brush);
+
+    /**
+     * Fills the triangle strip or fan with the current brush
+     */
+    fn draw_triangles(&mut self, triangles : &Triangles);
+
+    /**
+     * Draws one pixel wide lines with the current brush, each between a pair of the points given as a flat list of coordinates
+     */
+    fn draw_lines(&mut self, points : &[f32]);
+
+    /**
+     * Draws squares of the size in pixels with the current brush, centred on the points given as a flat list of coordinates
+     */
+    fn draw_points(&mut self, points : &[f32], size : f32);
+}
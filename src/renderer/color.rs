@@ -0,0 +1,87 @@
+/** sRGB component with its transfer function undone, mirrored for negative values */
+pub fn srgb_to_linear(c : f32) -> f32 {
+    let abs = c.abs();
+    let linear = if (abs <= 0.04045) {
+        abs / 12.92
+    } else {
+        ((abs + 0.055) / 1.055).powf(2.4)
+    };
+    linear.copysign(c)
+}
+
+/** linear-light component with the sRGB transfer function applied, mirrored for negative values */
+pub fn linear_to_srgb(c : f32) -> f32 {
+    let abs = c.abs();
+    let srgb = if (abs <= 0.0031308) {
+        abs * 12.92
+    } else {
+        1.055 * abs.powf(1.0 / 2.4) - 0.055
+    };
+    srgb.copysign(c)
+}
+
+/** OKLab lightness and a, b axes of a linear-light sRGB colour, which may be out of 0 to 1 */
+pub fn linear_srgb_to_oklab([r, g, b] : [f32; 3]) -> [f32; 3] {
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+    [
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    ]
+}
+
+/** linear-light sRGB colour of OKLab lightness and a, b axes, out of 0 to 1 for colours out of the sRGB gamut */
+pub fn oklab_to_linear_srgb([lightness, a, b] : [f32; 3]) -> [f32; 3] {
+    let l = (lightness + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m = (lightness - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s = (lightness - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+    [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual : [f32; 3], expected : [f32; 3], tolerance : f32) {
+        assert!(actual.iter().zip(expected).all(|(a, e)| (a - e).abs() <= tolerance), "{:?} instead of {:?}", actual, expected);
+    }
+
+    #[test]
+    fn srgb_transfer_round_trips() {
+        for c in [-0.5, 0.0, 0.002, 0.04045, 0.2, 0.5, 1.0, 1.5] {
+            assert!((linear_to_srgb(srgb_to_linear(c)) - c).abs() < 1e-6, "{}", c);
+        }
+        assert!((srgb_to_linear(0.5) - 0.21404).abs() < 1e-5);
+    }
+
+    #[test]
+    fn oklab_of_primaries() {
+        // reference values published with OKLab
+        let primaries = [
+            ([1.0, 0.0, 0.0], [0.62796, 0.22486, 0.12585]),
+            ([0.0, 1.0, 0.0], [0.86644, -0.23389, 0.17950]),
+            ([0.0, 0.0, 1.0], [0.45201, -0.03246, -0.31153]),
+            ([1.0, 1.0, 1.0], [1.0, 0.0, 0.0]),
+        ];
+        for (rgb, lab) in primaries {
+            let linear = rgb.map(srgb_to_linear);
+            assert_close(linear_srgb_to_oklab(linear), lab, 1e-4);
+            assert_close(oklab_to_linear_srgb(linear_srgb_to_oklab(linear)), linear, 1e-4);
+        }
+    }
+
+    #[test]
+    fn blue_and_yellow_mixed_in_oklab() {
+        let blue = linear_srgb_to_oklab([0.0, 0.0, 1.0]);
+        let yellow = linear_srgb_to_oklab([1.0, 1.0, 0.0]);
+        let middle = [0, 1, 2].map(|i| (blue[i] + yellow[i]) / 2.0);
+        // the middle of `linear-gradient(in oklab, blue, yellow)` is a light blue instead of the grey of sRGB
+        assert_close(oklab_to_linear_srgb(middle).map(linear_to_srgb), [0.4226, 0.6724, 0.7805], 2e-3);
+    }
+}
@@ -0,0 +1,127 @@
+use super::{backend::Backend, Brush, Primitive, Triangles, TrianglesMode};
+
+const WIREFRAME_COLOR : [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+const VERTEX_COLOR : [f32; 4] = [1.0, 0.9, 0.0, 1.0];
+const BOUNDS_COLOR : [f32; 4] = [0.0, 1.0, 0.4, 1.0];
+/** colours of consecutive fans, alternating so that neighbouring fans can be told apart */
+const FAN_COLORS : [[f32; 4]; 2] = [[1.0, 0.5, 0.0, 0.5], [0.8, 0.2, 0.0, 0.5]];
+const STRIP_COLORS : [[f32; 4]; 2] = [[0.0, 0.6, 1.0, 0.5], [0.0, 0.3, 0.8, 0.5]];
+
+/** size of the vertex dots in pixels */
+const VERTEX_SIZE : f32 = 4.0;
+
+/**
+ * Parts of the tesselation drawn over the shapes for finding tesselation problems, nothing is drawn by default
+ */
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct DebugOverlay {
+    /** edges of all triangles */
+    pub wireframe : bool,
+    /** dots on the vertices of the triangles */
+    pub vertices : bool,
+    /** rectangle around each shape, including its outline */
+    pub bounds : bool,
+    /** triangles coloured by whether they come from a fan or a strip */
+    pub triangle_modes : bool,
+}
+
+impl DebugOverlay {
+    pub fn is_enabled(&self) -> bool {
+        self.wireframe || self.vertices || self.bounds || self.triangle_modes
+    }
+
+    /**
+     * Turns the part given by its name ("wireframe", "vertices", "bounds" or "modes") on or off,
+     * returns false for unknown names
+     */
+    pub fn set(&mut self, name : &str, enabled : bool) -> bool {
+        match name {
+            "wireframe" => self.wireframe = enabled,
+            "vertices" => self.vertices = enabled,
+            "bounds" => self.bounds = enabled,
+            "modes" => self.triangle_modes = enabled,
+            _ => return false
+        }
+        true
+    }
+
+    /**
+     * Draws the enabled parts for the primitive, with the transform the primitive was drawn with
+     */
+    pub fn draw<B : Backend>(&self, backend : &mut B, primitive : &Primitive) {
+        let parts : Vec<&Triangles> = match &primitive.stroke {
+            Some(stroke) => primitive.parts.iter().chain(stroke.parts.iter()).collect(),
+            None => primitive.parts.iter().collect()
+        };
+
+        if (self.triangle_modes) {
+            let (mut fans, mut strips) = (0, 0);
+            for triangles in &parts {
+                let color = match triangles.mode {
+                    TrianglesMode::Fan => {fans += 1; FAN_COLORS[fans % 2]},
+                    TrianglesMode::Strip => {strips += 1; STRIP_COLORS[strips % 2]},
+                };
+                backend.set_brush(&color_brush(color));
+                backend.draw_triangles(triangles);
+            }
+        }
+
+        if (self.wireframe) {
+            let mut lines = Vec::new();
+            for triangles in &parts {
+                for [a, b, c] in triangle_vertices(triangles) {
+                    lines.extend_from_slice(&[a[0], a[1], b[0], b[1], b[0], b[1], c[0], c[1], c[0], c[1], a[0], a[1]]);
+                }
+            }
+            backend.set_brush(&color_brush(WIREFRAME_COLOR));
+            backend.draw_lines(&lines);
+        }
+
+        if (self.vertices) {
+            let points : Vec<f32> = parts.iter().flat_map(|triangles| triangles.vertices.iter().copied()).collect();
+            backend.set_brush(&color_brush(VERTEX_COLOR));
+            backend.draw_points(&points, VERTEX_SIZE);
+        }
+
+        if (self.bounds) {
+            if let Some([left, top, right, bottom]) = vertices_bounds(&parts) {
+                backend.set_brush(&color_brush(BOUNDS_COLOR));
+                backend.draw_lines(&[
+                    left, top, right, top,
+                    right, top, right, bottom,
+                    right, bottom, left, bottom,
+                    left, bottom, left, top,
+                ]);
+            }
+        }
+    }
+}
+
+fn color_brush(color : [f32; 4]) -> Brush {
+    Brush::Color(color[0], color[1], color[2], color[3])
+}
+
+/**
+ * Corners of each of the triangles of a strip or a fan
+ */
+pub fn triangle_vertices(triangles : &Triangles) -> Vec<[[f32; 2]; 3]> {
+    let points : Vec<[f32; 2]> = triangles.vertices.chunks_exact(2).map(|v| [v[0], v[1]]).collect();
+    (2..points.len()).map(|i| match triangles.mode {
+        TrianglesMode::Strip => [points[i - 2], points[i - 1], points[i]],
+        TrianglesMode::Fan => [points[0], points[i - 1], points[i]],
+    }).collect()
+}
+
+/**
+ * Left, top, right and bottom edge of all the vertices, None if there are none
+ */
+fn vertices_bounds(parts : &[&Triangles]) -> Option<[f32; 4]> {
+    let mut bounds : Option<[f32; 4]> = None;
+    for v in parts.iter().flat_map(|triangles| triangles.vertices.chunks_exact(2)) {
+        bounds = Some(match bounds {
+            Some([l, t, r, b]) => [l.min(v[0]), t.min(v[1]), r.max(v[0]), b.max(v[1])],
+            None => [v[0], v[1], v[0], v[1]],
+        });
+    }
+    bounds
+}
@@ -0,0 +1,83 @@
+use crate::{data::Document, matrix::Matrix3x3};
+
+use super::{draw_scene, software::SoftwareBackend, Brush, Renderer, BACKGROUND_COLOR};
+
+/**
+ * Area of the canvas drawn into an image and how it's drawn
+ */
+#[derive(Clone)]
+pub struct ImageOptions {
+    /** size of the area in canvas units */
+    pub width : f32,
+    pub height : f32,
+    /** pixels per canvas unit */
+    pub scale : f32,
+    /** brush the image is filled with before drawing, transparent if None */
+    pub background : Option<Brush>,
+}
+
+impl ImageOptions {
+    pub fn new(width : f32, height : f32) -> ImageOptions {
+        let [r, g, b, a] = BACKGROUND_COLOR;
+        ImageOptions{width, height, scale : 1.0, background : Some(Brush::Color(r, g, b, a))}
+    }
+
+    /**
+     * Size of the image in pixels
+     */
+    pub fn pixel_size(&self) -> (u32, u32) {
+        ((self.width * self.scale).round() as u32, (self.height * self.scale).round() as u32)
+    }
+
+    pub fn background_brush(&self) -> Brush {
+        self.background.clone().unwrap_or(Brush::Color(0.0, 0.0, 0.0, 0.0))
+    }
+}
+
+/**
+ * Draws the document seen through the view transform with the software backend
+ */
+pub fn render_image(document : &Document, view : &Matrix3x3, options : &ImageOptions) -> SoftwareBackend {
+    let (width, height) = options.pixel_size();
+    let mut backend = SoftwareBackend::new(width, height);
+    backend.set_scale(options.scale);
+
+    let mut renderer = Renderer::new(backend);
+    renderer.set_transform(*view);
+    renderer.update(document);
+    draw_scene(&mut renderer, &options.background_brush());
+    renderer.into_backend()
+}
+
+/**
+ * Draws the document without a browser and encodes it as PNG
+ */
+pub fn export_png(document : &Document, view : &Matrix3x3, options : &ImageOptions) -> Result<Vec<u8>, png::EncodingError> {
+    let image = render_image(document, view, options);
+    encode_png(image.width(), image.height(), image.pixels())
+}
+
+/**
+ * Encodes RGBA pixels premultiplied by alpha, given in rows from the top, as PNG
+ */
+pub fn encode_png(width : u32, height : u32, pixels : &[u8]) -> Result<Vec<u8>, png::EncodingError> {
+    // PNG keeps colours which are not multiplied by alpha
+    let mut pixels = pixels.to_vec();
+    for pixel in pixels.chunks_exact_mut(4) {
+        let a = pixel[3] as u32;
+        if (a > 0 && a < 255) {
+            for c in 0..3 {
+                pixel[c] = ((pixel[c] as u32 * 255 + a / 2) / a).min(255) as u8;
+            }
+        }
+    }
+
+    let mut data = Vec::new();
+    let mut encoder = png::Encoder::new(&mut data, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&pixels)?;
+    writer.finish()?;
+    Ok(data)
+}
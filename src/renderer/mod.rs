@@ -1,165 +1,333 @@
-use std::{borrow::Borrow, f32::consts::PI, primitive, time::Instant};
-
-use shaders::{ create_shader_program, ShaderInfo};
+#[cfg(feature = "web")]
 use wasm_bindgen::prelude::*;
-use web_sys::{console::{time_end_with_label, time_with_label}, WebGl2RenderingContext, WebGlProgram, WebGlShader};
-use crate::{base::*, matrix::Matrix3x3, point::Point, Orientation};
+use std::collections::{HashMap, HashSet};
+
+use backend::Backend;
+use debug::DebugOverlay;
+use tesselation::{concat_triangles, contour_orientation, oriented_contours};
+use crate::{data::{Document, NodeId}, matrix::Matrix3x3, path::{Path, PathSegment}, point::Point, Orientation};
 
+#[cfg(feature = "web")]
 mod shaders;
+#[cfg(feature = "web")]
+pub mod webgl;
+pub mod backend;
+pub mod color;
+pub mod debug;
+pub mod image;
+pub mod ramp;
+pub mod software;
 pub mod tesselation;
-
-pub struct Renderer {
-    gl : WebGl2RenderingContext,
-    program : WebGlProgram,
-    primitives : Vec<Primitive>,
-    shader_info : ShaderInfo,
+pub mod stroke;
+
+/**
+ * Colour the canvas is cleared with before drawing, unless another background is set
+ */
+pub const BACKGROUND_COLOR : [f32; 4] = [0.0, 0.0, 0.2, 1.0];
+
+/**
+ * Part of a primitive drawn with its own brush
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum PrimitivePart {
+    Fill, Stroke
 }
 
-impl Renderer {
-    pub fn create(gl : WebGl2RenderingContext) -> Renderer{
+/**
+ * Most vertices joined into one batch, so that batches can be indexed by 16 bits and appending
+ * a shape to a long run of the same brush uploads only the last batch of it again
+ */
+const MAX_BATCH_VERTICES : usize = 65536;
+
+/**
+ * Consecutive parts of primitives painted with the same brush, drawn together from one mesh
+ */
+struct Batch<M> {
+    brush : Brush,
+    parts : Vec<(NodeId, PrimitivePart)>,
+    mesh : M,
+}
 
-        let (program, shader_info) = create_shader_program(&gl);
-        gl.use_program(Some(&program));
+/**
+ * Keeps tesselated shapes of a document and draws them with the backend
+ */
+pub struct Renderer<B : Backend> {
+    backend : B,
+    /** tesselated shapes of the document */
+    primitives : HashMap<NodeId, Primitive>,
+    /** shapes which changed since they were tesselated */
+    outdated : HashSet<NodeId>,
+    /** visible shapes in the order they are drawn */
+    draw_order : Vec<NodeId>,
+    /** visible shapes grouped by brushes, in the drawing order */
+    batches : Vec<Batch<B::Mesh>>,
+    transform : Matrix3x3,
+    debug : DebugOverlay,
+}
 
-        let renderer = Renderer{
-            gl,
-            program : program,
-            primitives : vec![Primitive{parts : vec![Triangles{vertices:vec![10.0, 30.0, 170.0, 30.0, 100.0, 170.0], mode: TrianglesMode::Strip}], fill: Brush::Color(0.2, 0.7, 0.5, 1.0)}],
-            shader_info : shader_info
+impl<B : Backend> Renderer<B> {
+    pub fn new(backend : B) -> Renderer<B> {
+        let mut renderer = Renderer{
+            backend,
+            primitives : HashMap::new(),
+            outdated : HashSet::new(),
+            draw_order : Vec::new(),
+            batches : Vec::new(),
+            transform : Matrix3x3::identity(),
+            debug : DebugOverlay::default(),
         };
 
         renderer.set_transform(Matrix3x3::identity());
 
-        return renderer;
-    }
-
-    pub fn set_vertices(&self, attribute : u32, vertices : &[f32], coords_per_vertex : i32) {
-
-        let buffer = self.gl.create_buffer().ok_or("Failed to create buffer").unwrap();
-        self.gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(&buffer));
-
-        // Note that `Float32Array::view` is somewhat dangerous (hence the
-        // `unsafe`!). This is creating a raw view into our module's
-        // `WebAssembly.Memory` buffer, but if we allocate more pages for ourself
-        // (aka do a memory allocation in Rust) it'll cause the buffer to change,
-        // causing the `Float32Array` to be invalid.
-        //
-        // As a result, after `Float32Array::view` we have to be very careful not to
-        // do any memory allocations before it's dropped.
-        unsafe {
-            let positions_array_buf_view = js_sys::Float32Array::view(&vertices);
-            
-            self.gl.buffer_data_with_array_buffer_view(
-                WebGl2RenderingContext::ARRAY_BUFFER,
-                &positions_array_buf_view,
-                WebGl2RenderingContext::STATIC_DRAW,
-            );
-        }
-        
-        let vao = self.gl
-            .create_vertex_array()
-            .ok_or("Could not create vertex array object")
-            .unwrap();
-        self.gl.bind_vertex_array(Some(&vao));
-
-
-        let position_attribute_location = 
-        self.gl.vertex_attrib_pointer_with_i32(
-            attribute,
-            coords_per_vertex,
-            WebGl2RenderingContext::FLOAT,
-            false,
-            0,
-            0,
-        );
-        self.gl.enable_vertex_attrib_array(attribute);
-
-        self.gl.bind_vertex_array(Some(&vao));
-
-    }
-
-    fn draw_primitive(&self, primitive : &Primitive) {
-
-        const coords_per_vertex : i32 = 2;
-    
-        self.set_brush(&primitive.fill);
-        for strip in &primitive.parts {
-            self.set_vertices(self.shader_info.a_pos, &strip.vertices, coords_per_vertex as i32);
-            match strip.mode {
-                TrianglesMode::Fan =>self.gl.draw_arrays(WebGl2RenderingContext::TRIANGLE_FAN, 0, strip.vertices.len() as i32 / coords_per_vertex ),
-                TrianglesMode::Strip =>self.gl.draw_arrays(WebGl2RenderingContext::TRIANGLE_STRIP, 0, strip.vertices.len() as i32 / coords_per_vertex ),
-            }
-            
-        }
-        //red lines
-        for strip in &primitive.parts { 
-            self.set_vertices(self.shader_info.a_pos, &strip.vertices, coords_per_vertex as i32);
-            self.set_brush(&Brush::Color(1.0, 0.0, 0.0, 1.0));
-            self.gl.draw_arrays(WebGl2RenderingContext::LINE_LOOP, 0, strip.vertices.len() as i32 / coords_per_vertex);
-        }
+        renderer
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    pub fn backend_mut(&mut self) -> &mut B {
+        &mut self.backend
     }
 
-    pub fn resize_viewport(&self, width : f32, height : f32) {
+    pub fn into_backend(self) -> B {
+        self.backend
+    }
 
-        let resolution_attribute_location = self.gl.uniform2f(self.shader_info.u_res.as_ref(), width, height);
+    pub fn set_transform(&mut self, matrix : Matrix3x3) {
+        self.backend.set_transform(&matrix);
+        self.transform = matrix;
+    }
 
-        self.gl.viewport(0,0, width as i32, height as i32);
+    pub fn transform(&self) -> &Matrix3x3 {
+        &self.transform
     }
 
-    fn set_brush(&self, brush : &Brush) {
-        match brush {
-            Brush::Color(r, g, b, a) => {
-                self.gl.uniform1ui(self.shader_info.u_brush_type.as_ref(), 1);
-                self.gl.uniform4f(self.shader_info.u_color.as_ref(), f32::to_owned(r), f32::to_owned(g), f32::to_owned(b), f32::to_owned(a));
-            },
-            Brush::LinearGradient(gradient) => {
-                self.gl.uniform1ui(self.shader_info.u_brush_type.as_ref(), 2);
+    pub fn debug_overlay(&self) -> &DebugOverlay {
+        &self.debug
+    }
 
-                self.gl.uniform2f(self.shader_info.gradient_start.as_ref(), gradient.x1, gradient.y1);
-                self.gl.uniform2f(self.shader_info.gradient_end.as_ref(), gradient.x2, gradient.y2);
+    pub fn set_debug_overlay(&mut self, debug : DebugOverlay) {
+        self.debug = debug;
+    }
 
-                self.gl.uniform1i(self.shader_info.gradient_stops_count.as_ref(), gradient.stops.len() as i32);
-                self.gl.uniform4fv_with_f32_array(self.shader_info.colors.as_ref(), &gradient.stops.iter().flat_map(|s| [s.r, s.g, s.b, s.a].into_iter()).collect::<Vec<f32>>());
-                self.gl.uniform1fv_with_f32_array(self.shader_info.gradient_stops.as_ref(), &gradient.stops.iter().map(|s| s.position).collect::<Vec<f32>>());
-            },
-            Brush::RadialGradient(gradient) => {
-                self.gl.uniform1ui(self.shader_info.u_brush_type.as_ref(), 3);
+    /**
+     * Rebuilds the drawing order from the document tree, tesselating visible shapes which have no primitive yet
+     * or which changed, and dropping primitives of removed nodes. Batches with changed shapes are uploaded again.
+     */
+    pub fn update(&mut self, document : &Document) {
+        let mut changed = HashSet::new();
+        self.draw_order.clear();
+        for (id, shape, transform) in document.visible_shapes() {
+            if (!self.primitives.contains_key(&id) || self.outdated.remove(&id)) {
+                self.primitives.insert(id, shape.to_primitive(&transform));
+                changed.insert(id);
+            }
+            self.draw_order.push(id);
+        }
 
-                self.gl.uniform2f(self.shader_info.gradient_start.as_ref(), gradient.x1, gradient.y1);
-                self.gl.uniform2f(self.shader_info.gradient_end.as_ref(), gradient.x2, gradient.y2);
+        self.primitives.retain(|id, _| document.contains(*id));
+        // shapes without a primitive are tesselated anyway once they are visible
+        let primitives = &self.primitives;
+        self.outdated.retain(|id| primitives.contains_key(id));
 
-                self.gl.uniform1i(self.shader_info.gradient_stops_count.as_ref(), gradient.stops.len() as i32);
-                self.gl.uniform4fv_with_f32_array(self.shader_info.colors.as_ref(), &gradient.stops.iter().flat_map(|s| [s.r, s.g, s.b, s.a].into_iter()).collect::<Vec<f32>>());
-                self.gl.uniform1fv_with_f32_array(self.shader_info.gradient_stops.as_ref(), &gradient.stops.iter().map(|s| s.position).collect::<Vec<f32>>());
-            },
-            Brush::ConicGradient(gradient) => {
-                self.gl.uniform1ui(self.shader_info.u_brush_type.as_ref(), 4);
+        self.update_batches(&changed);
+    }
 
-                self.gl.uniform2f(self.shader_info.gradient_start.as_ref(), gradient.x1, gradient.y1);
-                self.gl.uniform2f(self.shader_info.gradient_end.as_ref(), gradient.x2, gradient.y2);
+    /**
+     * Marks primitives of the nodes as outdated, so that they are tesselated again on the next update
+     */
+    pub fn invalidate(&mut self, ids : &[NodeId]) {
+        self.outdated.extend(ids.iter().copied());
+    }
 
-                self.gl.uniform1i(self.shader_info.gradient_stops_count.as_ref(), gradient.stops.len() as i32);
-                self.gl.uniform4fv_with_f32_array(self.shader_info.colors.as_ref(), &gradient.stops.iter().flat_map(|s| [s.r, s.g, s.b, s.a].into_iter()).collect::<Vec<f32>>());
-                self.gl.uniform1fv_with_f32_array(self.shader_info.gradient_stops.as_ref(), &gradient.stops.iter().map(|s| s.position).collect::<Vec<f32>>());
+    /**
+     * Groups parts of the primitives following each other in the drawing order which have the same brush.
+     * Meshes of the previous batches are reused by the first part of the batch, and uploaded again if their content changed.
+     */
+    fn update_batches(&mut self, changed : &HashSet<NodeId>) {
+        let mut groups : Vec<(Brush, Vec<(NodeId, PrimitivePart)>, usize)> = Vec::new();
+        for id in &self.draw_order {
+            let Some(primitive) = self.primitives.get(id) else {
+                continue;
+            };
+            let fill = primitive.fill.as_ref().filter(|_| !primitive.parts.is_empty()).map(|brush| (brush, PrimitivePart::Fill));
+            let stroke = primitive.stroke.as_ref().filter(|stroke| !stroke.parts.is_empty()).map(|stroke| (&stroke.brush, PrimitivePart::Stroke));
+            for (brush, part) in fill.into_iter().chain(stroke) {
+                let vertices = self.part_mesh(id, &part).vertices.len() / 2;
+                match groups.last_mut() {
+                    Some((last, parts, count)) if (last == brush && *count + vertices <= MAX_BATCH_VERTICES) => {
+                        parts.push((*id, part));
+                        *count += vertices;
+                    },
+                    _ => groups.push((brush.clone(), vec![(*id, part)], vertices)),
+                }
             }
-        };
+        }
+
+        let mut previous : HashMap<(NodeId, PrimitivePart), Batch<B::Mesh>> = std::mem::take(&mut self.batches).into_iter()
+            .map(|batch| (batch.parts[0], batch))
+            .collect();
+        for (brush, parts, _) in groups {
+            let batch = match previous.remove(&parts[0]) {
+                Some(mut batch) => {
+                    if (batch.parts != parts || parts.iter().any(|(id, _)| changed.contains(id))) {
+                        self.backend.update_mesh(&mut batch.mesh, &self.batch_triangles(&parts));
+                    }
+                    Batch{brush, parts, mesh : batch.mesh}
+                },
+                None => {
+                    let mesh = self.backend.create_mesh(&self.batch_triangles(&parts));
+                    Batch{brush, parts, mesh}
+                }
+            };
+            self.batches.push(batch);
+        }
+        for (_, batch) in previous {
+            self.backend.delete_mesh(batch.mesh);
+        }
     }
 
-    pub fn set_transform(&self, matrix : Matrix3x3) {
-        self.gl.uniform_matrix3fv_with_f32_array(self.shader_info.transform.as_ref(), false, &matrix.data())
+    /**
+     * Triangles of the batch joined from the ones kept by its primitives
+     */
+    fn batch_triangles(&self, parts : &[(NodeId, PrimitivePart)]) -> IndexedTriangles {
+        concat_triangles(parts.iter().map(|(id, part)| self.part_mesh(id, part)))
     }
 
-    pub fn add_primitive(&mut self, primitive : Primitive) {
-        self.primitives.push(primitive);
+    fn part_mesh(&self, id : &NodeId, part : &PrimitivePart) -> &IndexedTriangles {
+        let primitive = &self.primitives[id];
+        match (part, &primitive.stroke) {
+            (PrimitivePart::Stroke, Some(stroke)) => &stroke.mesh,
+            _ => &primitive.mesh,
+        }
     }
+}
 
+/**
+ * Clears the backend with the background brush and draws the primitives over it, followed by the debug overlay if it's enabled
+ */
+pub fn draw<B : Backend>(renderer : &mut Renderer<B>, background : &Brush) {
+    draw_scene(renderer, background);
+    draw_debug_overlay(renderer);
 }
 
+/**
+ * Clears the backend with the background brush and draws the primitives over it, without the debug overlay
+ */
+pub fn draw_scene<B : Backend>(renderer : &mut Renderer<B>, background : &Brush) {
+    renderer.backend.clear(background);
 
+    for batch in renderer.batches.iter() {
+        renderer.backend.set_brush(&batch.brush);
+        renderer.backend.draw_mesh(&batch.mesh);
+    }
+}
+
+/**
+ * Draws the enabled parts of the debug overlay over the primitives
+ */
+pub fn draw_debug_overlay<B : Backend>(renderer : &mut Renderer<B>) {
+    if (renderer.debug.is_enabled()) {
+        for id in renderer.draw_order.iter() {
+            if let Some(primitive) = renderer.primitives.get(id) {
+                renderer.debug.draw(&mut renderer.backend, primitive);
+            }
+        }
+    }
+}
 
 pub struct Primitive {
     pub parts : Vec<Triangles>,
-    pub fill : Brush
+    /** the parts as a triangle list, joined into meshes of batches without indexing them again */
+    pub mesh : IndexedTriangles,
+    pub fill : Option<Brush>,
+    pub fill_rule : FillRule,
+    pub stroke : Option<Stroke>,
+}
+
+/**
+ * Outline of a primitive, tesselated separately from its fill
+ */
+pub struct Stroke {
+    pub parts : Vec<Triangles>,
+    pub mesh : IndexedTriangles,
+    pub brush : Brush,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineJoin {
+    Miter, Round, Bevel
+}
+
+impl LineJoin {
+    pub fn from_name(name : &str) -> Option<LineJoin> {
+        match name {
+            "miter" => Some(LineJoin::Miter),
+            "round" => Some(LineJoin::Round),
+            "bevel" => Some(LineJoin::Bevel),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineCap {
+    Butt, Round, Square
+}
+
+impl LineCap {
+    pub fn from_name(name : &str) -> Option<LineCap> {
+        match name {
+            "butt" => Some(LineCap::Butt),
+            "round" => Some(LineCap::Round),
+            "square" => Some(LineCap::Square),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct StrokeStyle {
+    pub width : f32,
+    pub join : LineJoin,
+    pub cap : LineCap,
+    /** longest allowed miter, relative to the stroke width, longer miters are beveled */
+    pub miter_limit : f32,
+    /** alternating lengths of dashes and gaps, solid line if empty */
+    pub dash_array : Vec<f32>,
+    /** distance into the dash pattern at which the stroke starts */
+    pub dash_offset : f32,
+}
+
+impl StrokeStyle {
+    /**
+     * Stroke style with SVG defaults
+     */
+    pub fn new(width : f32) -> StrokeStyle {
+        StrokeStyle{width, join : LineJoin::Miter, cap : LineCap::Butt, miter_limit : 4.0, dash_array : Vec::new(), dash_offset : 0.0}
+    }
+
+    /**
+     * Style with the width and dash lengths multiplied by the scale
+     */
+    pub fn scaled(&self, scale : f32) -> StrokeStyle {
+        StrokeStyle{
+            width : self.width * scale,
+            dash_array : self.dash_array.iter().map(|d| d * scale).collect(),
+            dash_offset : self.dash_offset * scale,
+            ..self.clone()
+        }
+    }
+
+    /**
+     * Whether the dash pattern is usable, patterns with negative lengths or no length at all are drawn solid like in SVG
+     */
+    pub fn is_dashed(&self) -> bool {
+        !self.dash_array.is_empty()
+            && self.dash_array.iter().all(|d| *d >= 0.0)
+            && self.dash_array.iter().sum::<f32>() > 0.0
+    }
 }
 
 pub struct Triangles {
@@ -171,16 +339,240 @@ pub enum TrianglesMode {
     Strip, Fan
 }
 
+/**
+ * Indices into a vertex list, 16 bit while there are few enough vertices
+ */
 #[derive(Clone)]
+pub enum Indices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl Indices {
+    pub fn len(&self) -> usize {
+        match self {
+            Indices::U16(indices) => indices.len(),
+            Indices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn get(&self, i : usize) -> usize {
+        match self {
+            Indices::U16(indices) => indices[i] as usize,
+            Indices::U32(indices) => indices[i] as usize,
+        }
+    }
+}
+
+/**
+ * Triangle list sharing vertices, every three indices make a triangle
+ */
+#[derive(Clone)]
+pub struct IndexedTriangles {
+    pub vertices : Vec<f32>,
+    pub indices : Indices,
+}
+
+/**
+ * How a gradient continues past its end points, like `spreadMethod` in SVG
+ */
+#[derive(Clone, Copy, PartialEq)]
+pub enum Spread {
+    /** colours of the first and the last stop are extended */
+    Pad,
+    /** gradient starts over after each end */
+    Repeat,
+    /** gradient goes back and forth */
+    Reflect,
+}
+
+impl Spread {
+    pub fn from_name(name : &str) -> Option<Spread> {
+        match name {
+            "pad" => Some(Spread::Pad),
+            "repeat" => Some(Spread::Repeat),
+            "reflect" => Some(Spread::Reflect),
+            _ => None
+        }
+    }
+
+    /**
+     * Position within the gradient, from 0 to 1, for the position along it
+     */
+    pub fn apply(&self, t : f32) -> f32 {
+        match self {
+            Spread::Pad => t.clamp(0.0, 1.0),
+            Spread::Repeat => t - t.floor(),
+            Spread::Reflect => 1.0 - ((t - 2.0 * (t / 2.0).floor()) - 1.0).abs(),
+        }
+    }
+}
+
+/**
+ * How colours change between two neighbouring stops of a gradient
+ */
+#[derive(Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    /** evenly, like in SVG and Canvas2D */
+    Linear,
+    /** easing in and out of each stop */
+    Smooth,
+}
+
+impl Interpolation {
+    pub fn from_name(name : &str) -> Option<Interpolation> {
+        match name {
+            "linear" => Some(Interpolation::Linear),
+            "smooth" => Some(Interpolation::Smooth),
+            _ => None
+        }
+    }
+
+    /**
+     * How far the colour at the position moved from the stop at the first edge to the stop at the second one.
+     * Stops at the same position make a hard edge, where the colour of the second stop starts.
+     */
+    pub fn amount(&self, edge0 : f32, edge1 : f32, t : f32) -> f32 {
+        if (edge0 >= edge1) {
+            return if (t >= edge1) {1.0} else {0.0};
+        }
+        let x = ((t - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+        match self {
+            Interpolation::Linear => x,
+            Interpolation::Smooth => x * x * (3.0 - 2.0 * x),
+        }
+    }
+}
+
+/**
+ * Direction in which hues of cylindrical colour spaces go from one stop to the next, as in CSS Color 4
+ */
+#[derive(Clone, Copy, PartialEq)]
+pub enum HueInterpolation {
+    Shorter, Longer, Increasing, Decreasing
+}
+
+impl HueInterpolation {
+    pub fn from_name(name : &str) -> Option<HueInterpolation> {
+        match name {
+            "shorter" => Some(HueInterpolation::Shorter),
+            "longer" => Some(HueInterpolation::Longer),
+            "increasing" => Some(HueInterpolation::Increasing),
+            "decreasing" => Some(HueInterpolation::Decreasing),
+            _ => None
+        }
+    }
+}
+
+/**
+ * Colour space in which colours of gradient stops are mixed. Except for sRGB, which mixes the colours as they are
+ * like SVG and Canvas2D, colours are mixed premultiplied by alpha like in CSS Color 4.
+ */
+#[derive(Clone, Copy, PartialEq)]
+pub enum ColorSpace {
+    Srgb,
+    LinearSrgb,
+    Oklab,
+    Oklch(HueInterpolation),
+}
+
+impl ColorSpace {
+    /**
+     * Colour space given like in CSS, "srgb", "srgb-linear", "oklab" or "oklch", which can be followed
+     * by the direction of hues, as in "oklch longer hue"
+     */
+    pub fn from_name(name : &str) -> Option<ColorSpace> {
+        match name.split_whitespace().collect::<Vec<&str>>().as_slice() {
+            ["srgb"] => Some(ColorSpace::Srgb),
+            ["srgb-linear"] => Some(ColorSpace::LinearSrgb),
+            ["oklab"] => Some(ColorSpace::Oklab),
+            ["oklch"] => Some(ColorSpace::Oklch(HueInterpolation::Shorter)),
+            ["oklch", hue, "hue"] => HueInterpolation::from_name(hue).map(ColorSpace::Oklch),
+            _ => None
+        }
+    }
+}
+
+/**
+ * Points and stops of a gradient. The points are given in the coordinates of the gradient,
+ * which the transform places into the coordinates of the shape, like `gradientTransform` in SVG.
+ *
+ * Linear gradients go from the first point to the second one. Radial gradients go from the circle around the first point
+ * to the circle around the second one, like `createRadialGradient` in Canvas2D, so the first circle is the focus.
+ * Conic gradients turn around the first point.
+ */
+#[derive(Clone, PartialEq)]
 pub struct Gradient {
     pub x1 : f32,
     pub y1 : f32,
     pub x2 : f32,
     pub y2 : f32,
+    /** radii of the circles around the points, only used by radial gradients */
+    pub r1 : f32,
+    pub r2 : f32,
     pub stops : Vec<GradientStop>,
+    pub spread : Spread,
+    pub interpolation : Interpolation,
+    pub color_space : ColorSpace,
+    /** None when the gradient is placed in the coordinates of the shape as it is */
+    pub transform : Option<Matrix3x3>,
 }
 
-#[derive(Clone)]
+impl Gradient {
+    /**
+     * Same gradient placed by the transform, which is applied after its own transform
+     */
+    pub fn transformed(&self, matrix : &Matrix3x3) -> Gradient {
+        let transform = match &self.transform {
+            Some(transform) => matrix.multiply(transform),
+            None => *matrix,
+        };
+        Gradient{transform : Some(transform).filter(|t| !t.is_identity()), ..self.clone()}
+    }
+
+    /**
+     * Position along the radial gradient for the point, from the largest circle between the two circles of the gradient
+     * and beyond them which passes through the point. None where no such circle with a positive radius exists,
+     * which is left transparent like in Canvas2D.
+     */
+    pub fn radial_position(&self, x : f32, y : f32) -> Option<f32> {
+        // circles around c1 + t * (c2 - c1) with radius r1 + t * (r2 - r1) going through the point give
+        // a * t^2 - 2 * b * t + c = 0
+        let (cx, cy, dr) = (self.x2 - self.x1, self.y2 - self.y1, self.r2 - self.r1);
+        let (px, py) = (x - self.x1, y - self.y1);
+        let a = cx * cx + cy * cy - dr * dr;
+        let b = px * cx + py * cy + self.r1 * dr;
+        let c = px * px + py * py - self.r1 * self.r1;
+
+        let valid = |t : f32| self.r1 + t * dr >= 0.0;
+        if (a.abs() <= 1e-6 * (cx * cx + cy * cy + dr * dr)) {
+            // circles touching each other in a single point, the equation is linear
+            let t = c / (2.0 * b);
+            return Some(t).filter(|t| t.is_finite() && valid(*t));
+        }
+        let discriminant = b * b - a * c;
+        if (discriminant < 0.0) {
+            return None;
+        }
+        let root = discriminant.sqrt();
+        let (t1, t2) = ((b + root) / a, (b - root) / a);
+        let (larger, smaller) = if (t1 > t2) {(t1, t2)} else {(t2, t1)};
+        [larger, smaller].into_iter().find(|t| valid(*t))
+    }
+
+    /**
+     * Transform from the coordinates of the shape into the coordinates of the gradient,
+     * None if the transform of the gradient collapses the plane and nothing can be painted with it
+     */
+    pub fn inverse_transform(&self) -> Option<Matrix3x3> {
+        match &self.transform {
+            Some(transform) => transform.inverse(),
+            None => Some(Matrix3x3::identity()),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
 pub struct GradientStop {
     pub position : f32,
     pub r : f32,
@@ -189,7 +581,7 @@ pub struct GradientStop {
     pub a : f32,
 }
 
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub enum Brush {
     Color(f32, f32, f32, f32),
     LinearGradient(Gradient),
@@ -197,7 +589,28 @@ pub enum Brush {
     ConicGradient(Gradient),
 }
 
-#[wasm_bindgen]
+impl Brush {
+    pub fn gradient(&self) -> Option<&Gradient> {
+        match self {
+            Brush::Color(..) => None,
+            Brush::LinearGradient(gradient) | Brush::RadialGradient(gradient) | Brush::ConicGradient(gradient) => Some(gradient),
+        }
+    }
+
+    /**
+     * Brush placed by the affine transform, gradients keep their shape exactly as the transform is added to their own
+     */
+    pub fn transformed(&self, matrix : &Matrix3x3) -> Brush {
+        match self {
+            Brush::Color(..) => self.clone(),
+            Brush::LinearGradient(gradient) => Brush::LinearGradient(gradient.transformed(matrix)),
+            Brush::RadialGradient(gradient) => Brush::RadialGradient(gradient.transformed(matrix)),
+            Brush::ConicGradient(gradient) => Brush::ConicGradient(gradient.transformed(matrix)),
+        }
+    }
+}
+
+#[cfg_attr(feature = "web", wasm_bindgen)]
 #[derive(Clone, Copy)]
 pub struct P {
     x: f32,
@@ -217,29 +630,284 @@ impl P {
     pub fn new(x :f32, y : f32) -> P {
         P{x: x, y: y }
     }
+
+    pub fn from_point(point : &Point) -> P {
+        P{x: point.x as f32, y: point.y as f32}
+    }
 }
 
+/**
+ * Rule deciding which parts of self-intersecting or overlapping contours are filled, same as in SVG and Canvas
+ */
+#[derive(Clone, Copy, PartialEq)]
+pub enum FillRule {
+    /** Filled where a ray from the point crosses the outline an odd number of times */
+    EvenOdd,
+    /** Filled where the outline winds around the point at least once */
+    NonZero,
+}
+
+impl FillRule {
+    pub fn from_name(name : &str) -> Option<FillRule> {
+        match name {
+            "evenodd" => Some(FillRule::EvenOdd),
+            "nonzero" => Some(FillRule::NonZero),
+            _ => None
+        }
+    }
+
+    pub fn is_inside(&self, winding : i32, crossings : u32) -> bool {
+        match self {
+            FillRule::EvenOdd => crossings % 2 == 1,
+            FillRule::NonZero => winding != 0,
+        }
+    }
+}
+
+/**
+ * Polygon given by its outer contour and any number of inner contours (holes)
+ */
 pub struct Polygon {
     pub orientation : Orientation,
-    pub points : Vec<P>
+    pub points : Vec<P>,
+    pub holes : Vec<Vec<P>>,
+    pub fill_rule : FillRule,
 }
 
+impl Polygon {
+    /**
+     * Creates polygon with orientation detected from the signed area of the outer contour
+     */
+    pub fn new(points : Vec<P>, holes : Vec<Vec<P>>, fill_rule : FillRule) -> Polygon {
+        let orientation = contour_orientation(&points);
+        Polygon{orientation, points, holes, fill_rule}
+    }
 
-pub fn draw(renderer: &Renderer) {
+    /**
+     * Path of closed subpaths made of the outer contour and the holes, which wind against it so they stay cut out
+     */
+    pub fn to_path(&self) -> Path {
+        let mut path = Path::new();
+        for contour in oriented_contours(self) {
+            for (i, p) in contour.iter().enumerate() {
+                let point = Point::new(p.x as f64, p.y as f64);
+                path.push(if (i == 0) {PathSegment::MoveTo(point)} else {PathSegment::LineTo(point)});
+            }
+            path.close();
+        }
+        path
+    }
+}
 
-    time_with_label("Render time");
+#[cfg(test)]
+mod tests {
+    use crate::data::{Document, Shape};
 
-    renderer.gl.clear_color(0.0, 0.0, 0.2, 1.0);
-    renderer.gl.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
+    use super::*;
 
-    for primitive in renderer.primitives.iter() {
+    /** backend keeping count of mesh uploads, meshes are the numbers of their vertices */
+    #[derive(Default)]
+    struct CountingBackend {
+        created : usize,
+        updated : usize,
+        deleted : usize,
+        lines : usize,
+    }
+
+    impl Backend for CountingBackend {
+        type Mesh = usize;
+
+        fn create_mesh(&mut self, triangles : &IndexedTriangles) -> usize {
+            self.created += 1;
+            triangles.vertices.len() / 2
+        }
+
+        fn update_mesh(&mut self, mesh : &mut usize, triangles : &IndexedTriangles) {
+            self.updated += 1;
+            *mesh = triangles.vertices.len() / 2;
+        }
+
+        fn delete_mesh(&mut self, mesh : usize) {
+            self.deleted += 1;
+        }
 
-        renderer.draw_primitive(primitive);
+        fn draw_mesh(&mut self, mesh : &usize) {}
+        fn clear(&mut self, brush : &Brush) {}
+        fn set_transform(&mut self, matrix : &Matrix3x3) {}
+        fn set_brush(&mut self, brush : &Brush) {}
+        fn draw_triangles(&mut self, triangles : &Triangles) {}
+        fn draw_lines(&mut self, points : &[f32]) {
+            self.lines += 1;
+        }
+        fn draw_points(&mut self, points : &[f32], size : f32) {}
     }
 
-    time_end_with_label("Render time");
-}
+    fn square(document : &mut Document, x : f64, fill : Brush) -> NodeId {
+        let mut path = Path::new();
+        path.move_to(x, 0.0);
+        path.line_to(x + 1.0, 0.0);
+        path.line_to(x + 1.0, 1.0);
+        path.line_to(x, 1.0);
+        path.close();
+        let root = document.root();
+        document.add_shape(root, Shape{path, fill : Some(fill), fill_rule : FillRule::NonZero, stroke : None}).unwrap()
+    }
 
+    fn counts(renderer : &Renderer<CountingBackend>) -> (usize, usize, usize) {
+        let backend = renderer.backend();
+        (backend.created, backend.updated, backend.deleted)
+    }
 
+    #[test]
+    fn batches_keep_their_meshes() {
+        let red = Brush::Color(1.0, 0.0, 0.0, 1.0);
+        let green = Brush::Color(0.0, 1.0, 0.0, 1.0);
+        let mut document = Document::new();
+        let first = square(&mut document, 0.0, red.clone());
+        square(&mut document, 2.0, green.clone());
+        let third = square(&mut document, 4.0, green);
+        square(&mut document, 6.0, red.clone());
+
+        let mut renderer = Renderer::new(CountingBackend::default());
+        renderer.update(&document);
+        assert_eq!(counts(&renderer), (3, 0, 0));
+        assert_eq!(renderer.batches.iter().map(|batch| batch.mesh).collect::<Vec<usize>>(), vec![4, 8, 4]);
+
+        // hiding the first batch leaves the meshes of the following ones alone
+        document.node_mut(first).unwrap().visible = false;
+        renderer.update(&document);
+        assert_eq!(counts(&renderer), (3, 0, 1));
+        document.node_mut(first).unwrap().visible = true;
+        renderer.update(&document);
+        assert_eq!(counts(&renderer), (4, 0, 1));
+
+        // only the batch of a changed shape is uploaded again
+        renderer.invalidate(&[third]);
+        renderer.update(&document);
+        assert_eq!(counts(&renderer), (4, 1, 1));
+        assert_eq!(renderer.batches.iter().map(|batch| batch.mesh).collect::<Vec<usize>>(), vec![4, 8, 4]);
+
+        // long runs of one brush are split at the vertex limit
+        let mut document = Document::new();
+        for i in 0..MAX_BATCH_VERTICES / 4 + 1 {
+            square(&mut document, i as f64 * 2.0, red.clone());
+        }
+        let mut renderer = Renderer::new(CountingBackend::default());
+        renderer.update(&document);
+        assert_eq!(renderer.batches.iter().map(|batch| batch.mesh).collect::<Vec<usize>>(), vec![MAX_BATCH_VERTICES, 4]);
+
+        // appending a shape uploads only the last batch again
+        square(&mut document, -2.0, red);
+        renderer.update(&document);
+        assert_eq!(counts(&renderer), (2, 1, 0));
+        assert_eq!(renderer.batches.iter().map(|batch| batch.mesh).collect::<Vec<usize>>(), vec![MAX_BATCH_VERTICES, 8]);
+    }
 
+    #[test]
+    fn scene_is_drawn_without_the_overlay() {
+        let mut document = Document::new();
+        square(&mut document, 0.0, Brush::Color(1.0, 0.0, 0.0, 1.0));
+        let mut renderer = Renderer::new(CountingBackend::default());
+        renderer.update(&document);
+        let mut overlay = DebugOverlay::default();
+        assert!(overlay.set("wireframe", true));
+        renderer.set_debug_overlay(overlay);
+
+        draw_scene(&mut renderer, &Brush::Color(0.0, 0.0, 0.0, 1.0));
+        assert_eq!(renderer.backend().lines, 0);
+        draw(&mut renderer, &Brush::Color(0.0, 0.0, 0.0, 1.0));
+        assert!(renderer.backend().lines > 0);
+    }
 
+    fn radial(x1 : f32, y1 : f32, r1 : f32, x2 : f32, y2 : f32, r2 : f32) -> Gradient {
+        Gradient{x1, y1, r1, x2, y2, r2, stops : Vec::new(), spread : Spread::Pad, interpolation : Interpolation::Linear,
+            color_space : ColorSpace::Srgb, transform : None}
+    }
+
+    fn assert_position(gradient : &Gradient, x : f32, y : f32, expected : Option<f32>) {
+        let t = gradient.radial_position(x, y);
+        let close = match (t, expected) {
+            (Some(t), Some(expected)) => (t - expected).abs() < 1e-5,
+            (t, expected) => t == expected,
+        };
+        assert!(close, "position {:?} at {} {} instead of {:?}", t, x, y, expected);
+    }
+
+    #[test]
+    fn radial_positions() {
+        // circle around the centre, growing from the focal point
+        let simple = radial(0.0, 0.0, 0.0, 0.0, 0.0, 10.0);
+        assert_position(&simple, 0.0, 0.0, Some(0.0));
+        assert_position(&simple, 0.0, -5.0, Some(0.5));
+        assert_position(&simple, 6.0, 8.0, Some(1.0));
+        assert_position(&simple, 20.0, 0.0, Some(2.0));
+
+        // focal circle inside the end circle, both circles are crossed at 0 and 1 in every direction
+        let inside = radial(5.0, 0.0, 0.0, 0.0, 0.0, 10.0);
+        assert_position(&inside, 5.0, 0.0, Some(0.0));
+        assert_position(&inside, 10.0, 0.0, Some(1.0));
+        assert_position(&inside, -10.0, 0.0, Some(1.0));
+        assert_position(&inside, 0.0, 10.0, Some(1.0));
+
+        // focal point outside of the end circle, painted only in the cone touching both circles
+        let outside = radial(20.0, 0.0, 0.0, 0.0, 0.0, 10.0);
+        assert_position(&outside, 20.0, 0.0, Some(0.0));
+        assert_position(&outside, 10.0, 0.0, Some(1.0));
+        assert_position(&outside, 0.0, 0.0, Some(2.0));
+        assert_position(&outside, 30.0, 0.0, None);
+        assert_position(&outside, 0.0, 20.0, None);
+    }
+
+    #[test]
+    fn radial_positions_of_special_circles() {
+        // the focal point lies on the end circle, circles touch in it and the equation is linear
+        let touching = radial(0.0, 0.0, 0.0, 10.0, 0.0, 10.0);
+        assert_position(&touching, 20.0, 0.0, Some(1.0));
+        assert_position(&touching, 10.0, 0.0, Some(0.5));
+        assert_position(&touching, 10.0, 10.0, Some(1.0));
+        assert_position(&touching, -5.0, 0.0, None);
+
+        // the larger solution is past the point where the shrinking circles get a negative radius
+        let shrinking = radial(0.0, 0.0, 10.0, 0.0, 0.0, 5.0);
+        assert_position(&shrinking, 20.0, 0.0, Some(-2.0));
+        assert_position(&shrinking, 5.0, 0.0, Some(1.0));
+
+        // equal circles paint nothing
+        let equal = radial(3.0, 4.0, 5.0, 3.0, 4.0, 5.0);
+        for (x, y) in [(3.0, 4.0), (8.0, 4.0), (20.0, 0.0)] {
+            assert_position(&equal, x, y, None);
+        }
+    }
+
+    #[test]
+    fn spread_positions() {
+        // positions before the start, at the end, at the end of the next period and in its middle
+        let positions = [-0.25, 1.0, 2.0, 1.5];
+        let expected = [
+            ("pad", [0.0, 1.0, 1.0, 1.0]),
+            // each period starts over with the first stop, even at the end of the gradient
+            ("repeat", [0.75, 0.0, 0.0, 0.5]),
+            ("reflect", [0.25, 1.0, 0.0, 0.5]),
+        ];
+        for (name, values) in expected {
+            let spread = Spread::from_name(name).unwrap();
+            for (t, value) in positions.iter().zip(values) {
+                assert_eq!(spread.apply(*t), value, "{} of {}", name, t);
+            }
+        }
+        assert!(Spread::from_name("mirror") == None);
+    }
+
+    #[test]
+    fn color_space_names() {
+        assert!(ColorSpace::from_name("srgb") == Some(ColorSpace::Srgb));
+        assert!(ColorSpace::from_name("srgb-linear") == Some(ColorSpace::LinearSrgb));
+        assert!(ColorSpace::from_name("oklab") == Some(ColorSpace::Oklab));
+        assert!(ColorSpace::from_name("oklch") == Some(ColorSpace::Oklch(HueInterpolation::Shorter)));
+        assert!(ColorSpace::from_name("oklch longer hue") == Some(ColorSpace::Oklch(HueInterpolation::Longer)));
+        assert!(ColorSpace::from_name("  oklch   decreasing  hue ") == Some(ColorSpace::Oklch(HueInterpolation::Decreasing)));
+        assert!(ColorSpace::from_name("oklch longer") == None);
+        assert!(ColorSpace::from_name("oklab longer hue") == None);
+        assert!(ColorSpace::from_name("lab") == None);
+    }
+}
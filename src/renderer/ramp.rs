@@ -0,0 +1,278 @@
+use super::{color::{linear_srgb_to_oklab, linear_to_srgb, oklab_to_linear_srgb, srgb_to_linear}, ColorSpace, Gradient, HueInterpolation};
+
+/** number of colours in the ramp of a gradient, each of them covering an equal part of it */
+pub const RAMP_SIZE : usize = 1024;
+
+/**
+ * What the ramp of a gradient depends on, compared by bit patterns so that it can be used as a key of baked ramps
+ */
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct RampKey(Vec<u32>);
+
+impl RampKey {
+    pub fn new(gradient : &Gradient) -> RampKey {
+        let color_space = match gradient.color_space {
+            ColorSpace::Srgb => 0,
+            ColorSpace::LinearSrgb => 1,
+            ColorSpace::Oklab => 2,
+            ColorSpace::Oklch(hue) => 3 + hue as u32,
+        };
+        let mut bits = vec![gradient.interpolation as u32, color_space];
+        for stop in &gradient.stops {
+            bits.extend([stop.position, stop.r, stop.g, stop.b, stop.a].map(f32::to_bits));
+        }
+        RampKey(bits)
+    }
+}
+
+/**
+ * Colours of the gradient in the middle of the parts of the ramp, in the space of `ramp_to_srgb`.
+ * Stops are moved to the nearest border between the parts, so that hard stops change the colour right there
+ * without any blending. Colours past the ends are the ones of the ends, like stops outside of 0 to 1 are clamped in SVG.
+ */
+pub fn bake_ramp(gradient : &Gradient) -> Vec<[f32; 4]> {
+    let colors : Vec<[f32; 4]> = gradient.stops.iter()
+        .map(|stop| to_interpolation_space(gradient.color_space, [stop.r, stop.g, stop.b, stop.a]))
+        .collect();
+    let positions : Vec<f32> = gradient.stops.iter()
+        .map(|stop| (stop.position * RAMP_SIZE as f32).round() / RAMP_SIZE as f32)
+        .collect();
+    (0..RAMP_SIZE).map(|i| {
+        let color = stops_color(gradient, &positions, &colors, (i as f32 + 0.5) / RAMP_SIZE as f32);
+        to_ramp_space(gradient.color_space, color)
+    }).collect()
+}
+
+/**
+ * Colour of the part of the ramp the position from 0 to 1 falls in, without filtering like `texelFetch` in the shader
+ */
+pub fn sample_ramp(ramp : &[[f32; 4]], t : f32) -> [f32; 4] {
+    let Some(last) = ramp.len().checked_sub(1) else {
+        return [0.0, 0.0, 0.0, 0.0];
+    };
+    ramp[((t.clamp(0.0, 1.0) * ramp.len() as f32) as usize).min(last)]
+}
+
+/**
+ * sRGB colour not premultiplied by alpha of a colour sampled from the ramp of a gradient in the colour space.
+ * Ramps of sRGB gradients hold sRGB colours, ramps of linear sRGB ones premultiplied linear-light colours and
+ * ramps of OKLab and OKLCH ones premultiplied OKLab colours, so that filtering never goes around hues.
+ */
+pub fn ramp_to_srgb(color_space : ColorSpace, [c1, c2, c3, alpha] : [f32; 4]) -> [f32; 4] {
+    if (color_space == ColorSpace::Srgb) {
+        return [c1, c2, c3, alpha];
+    }
+    if (alpha <= 0.0) {
+        return [0.0, 0.0, 0.0, 0.0];
+    }
+    let color = [c1 / alpha, c2 / alpha, c3 / alpha];
+    let linear = match color_space {
+        ColorSpace::LinearSrgb => color,
+        _ => oklab_to_linear_srgb(color),
+    };
+    let [r, g, b] = linear.map(|c| linear_to_srgb(c).clamp(0.0, 1.0));
+    [r, g, b, alpha]
+}
+
+/**
+ * Components in which colours of stops are mixed: premultiplied linear-light sRGB, OKLab, or OKLCH lightness and
+ * chroma premultiplied with the hue in degrees, except for sRGB colours which are mixed as they are
+ */
+fn to_interpolation_space(color_space : ColorSpace, [r, g, b, alpha] : [f32; 4]) -> [f32; 4] {
+    let linear = [r, g, b].map(srgb_to_linear);
+    let [c1, c2, c3] = match color_space {
+        ColorSpace::Srgb => return [r, g, b, alpha],
+        ColorSpace::LinearSrgb => linear,
+        ColorSpace::Oklab => linear_srgb_to_oklab(linear),
+        ColorSpace::Oklch(_) => {
+            let [lightness, a, b] = linear_srgb_to_oklab(linear);
+            let hue = b.atan2(a).to_degrees().rem_euclid(360.0);
+            return [lightness * alpha, a.hypot(b) * alpha, hue, alpha];
+        }
+    };
+    [c1 * alpha, c2 * alpha, c3 * alpha, alpha]
+}
+
+/**
+ * Colour mixed in the interpolation space as it is kept in the ramp
+ */
+fn to_ramp_space(color_space : ColorSpace, color : [f32; 4]) -> [f32; 4] {
+    match color_space {
+        ColorSpace::Oklch(_) => {
+            let [lightness, chroma, hue, alpha] = color;
+            let (sin, cos) = hue.to_radians().sin_cos();
+            [lightness, chroma * cos, chroma * sin, alpha]
+        }
+        _ => color
+    }
+}
+
+/**
+ * Hues of two OKLCH colours changed by whole turns so that mixing them goes in the direction. A colour without
+ * chroma takes the hue of the other one like in CSS Color 4, and the hue then stays the same in every direction.
+ */
+fn fix_hues(hue_interpolation : HueInterpolation, color : &mut [f32; 4], next : &mut [f32; 4]) {
+    const ACHROMATIC : f32 = 1e-5;
+    if (color[1] <= ACHROMATIC * color[3] && next[1] > ACHROMATIC * next[3]) {
+        color[2] = next[2];
+        return;
+    } else if (next[1] <= ACHROMATIC * next[3]) {
+        next[2] = color[2];
+        return;
+    }
+    let difference = next[2] - color[2];
+    match hue_interpolation {
+        HueInterpolation::Shorter => {
+            if (difference > 180.0) {
+                color[2] += 360.0;
+            } else if (difference < -180.0) {
+                next[2] += 360.0;
+            }
+        }
+        HueInterpolation::Longer => {
+            if (difference > 0.0 && difference < 180.0) {
+                color[2] += 360.0;
+            } else if (difference > -180.0 && difference <= 0.0) {
+                next[2] += 360.0;
+            }
+        }
+        HueInterpolation::Increasing => {
+            if (difference < 0.0) {
+                next[2] += 360.0;
+            }
+        }
+        HueInterpolation::Decreasing => {
+            if (difference > 0.0) {
+                color[2] += 360.0;
+            }
+        }
+    }
+}
+
+/**
+ * Mixes colours of the stops one after another, each from its own position to the position of the next one
+ */
+fn stops_color(gradient : &Gradient, positions : &[f32], colors : &[[f32; 4]], t : f32) -> [f32; 4] {
+    let Some(&first) = colors.first() else {
+        return [0.0, 0.0, 0.0, 0.0];
+    };
+    let mut color = first;
+    for (i, pair) in positions.windows(2).enumerate() {
+        let amount = gradient.interpolation.amount(pair[0], pair[1], t);
+        let mut next = colors[i + 1];
+        if let ColorSpace::Oklch(hue_interpolation) = gradient.color_space {
+            fix_hues(hue_interpolation, &mut color, &mut next);
+        }
+        for c in 0..4 {
+            color[c] += (next[c] - color[c]) * amount;
+        }
+    }
+    color
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::{GradientStop, Interpolation, Spread};
+
+    use super::*;
+
+    fn gradient(stops : &[(f32, [f32; 4])], interpolation : Interpolation, color_space : ColorSpace) -> Gradient {
+        let stops = stops.iter().map(|(position, [r, g, b, a])| GradientStop{position : *position, r : *r, g : *g, b : *b, a : *a}).collect();
+        Gradient{x1 : 0.0, y1 : 0.0, x2 : 1.0, y2 : 0.0, r1 : 0.0, r2 : 0.0, stops, spread : Spread::Pad, interpolation, color_space, transform : None}
+    }
+
+    fn color_at(gradient : &Gradient, t : f32) -> [f32; 4] {
+        ramp_to_srgb(gradient.color_space, sample_ramp(&bake_ramp(gradient), t))
+    }
+
+    #[test]
+    fn hard_stop_is_exact() {
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let blue = [0.0, 0.0, 1.0, 1.0];
+        for interpolation in [Interpolation::Linear, Interpolation::Smooth] {
+            let hard = gradient(&[(0.0, red), (0.5, red), (0.5, blue), (1.0, blue)], interpolation, ColorSpace::Srgb);
+            let ramp = bake_ramp(&hard);
+            assert!(ramp[..RAMP_SIZE / 2].iter().all(|color| *color == red));
+            assert!(ramp[RAMP_SIZE / 2..].iter().all(|color| *color == blue));
+            assert_eq!(sample_ramp(&ramp, 0.5 - 1e-6), red);
+            assert_eq!(sample_ramp(&ramp, 0.5), blue);
+        }
+    }
+
+    #[test]
+    fn ramp_covers_the_gradient() {
+        let black = [0.0, 0.0, 0.0, 1.0];
+        let white = [1.0, 1.0, 1.0, 1.0];
+        let ramp = bake_ramp(&gradient(&[(0.0, black), (1.0, white)], Interpolation::Linear, ColorSpace::Srgb));
+        assert_eq!(ramp.len(), RAMP_SIZE);
+        assert_eq!(sample_ramp(&ramp, -1.0), ramp[0]);
+        assert_eq!(sample_ramp(&ramp, 1.0), ramp[RAMP_SIZE - 1]);
+        for t in [0.0, 0.25, 0.5, 0.75, 1.0] {
+            assert!((sample_ramp(&ramp, t)[0] - t).abs() <= 0.5 / RAMP_SIZE as f32);
+        }
+    }
+
+    /** hues of the pair after fixing them, for colours with some chroma unless it's 0 */
+    fn fixed_hues(hue_interpolation : HueInterpolation, (chroma1, hue1) : (f32, f32), (chroma2, hue2) : (f32, f32)) -> (f32, f32) {
+        let mut color = [0.5, chroma1, hue1, 1.0];
+        let mut next = [0.5, chroma2, hue2, 1.0];
+        fix_hues(hue_interpolation, &mut color, &mut next);
+        (color[2], next[2])
+    }
+
+    #[test]
+    fn hue_directions() {
+        let colorful = 0.1;
+        let hues = |hue_interpolation, from, to| fixed_hues(hue_interpolation, (colorful, from), (colorful, to));
+
+        assert_eq!(hues(HueInterpolation::Shorter, 10.0, 50.0), (10.0, 50.0));
+        assert_eq!(hues(HueInterpolation::Shorter, 10.0, 350.0), (370.0, 350.0));
+        assert_eq!(hues(HueInterpolation::Shorter, 350.0, 10.0), (350.0, 370.0));
+
+        assert_eq!(hues(HueInterpolation::Longer, 10.0, 50.0), (370.0, 50.0));
+        assert_eq!(hues(HueInterpolation::Longer, 50.0, 10.0), (50.0, 370.0));
+        assert_eq!(hues(HueInterpolation::Longer, 10.0, 350.0), (10.0, 350.0));
+
+        assert_eq!(hues(HueInterpolation::Increasing, 10.0, 50.0), (10.0, 50.0));
+        assert_eq!(hues(HueInterpolation::Increasing, 50.0, 10.0), (50.0, 370.0));
+
+        assert_eq!(hues(HueInterpolation::Decreasing, 10.0, 50.0), (370.0, 50.0));
+        assert_eq!(hues(HueInterpolation::Decreasing, 50.0, 10.0), (50.0, 10.0));
+    }
+
+    #[test]
+    fn achromatic_colors_take_the_other_hue() {
+        for hue_interpolation in [HueInterpolation::Shorter, HueInterpolation::Longer, HueInterpolation::Increasing, HueInterpolation::Decreasing] {
+            assert_eq!(fixed_hues(hue_interpolation, (0.0, 0.0), (0.1, 120.0)), (120.0, 120.0));
+            assert_eq!(fixed_hues(hue_interpolation, (0.1, 240.0), (0.0, 0.0)), (240.0, 240.0));
+        }
+
+        // white to red stays on the hue of red instead of going around from the hue white happens to have
+        let white = [1.0, 1.0, 1.0, 1.0];
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let middle = color_at(&gradient(&[(0.0, white), (1.0, red)], Interpolation::Linear, ColorSpace::Oklch(HueInterpolation::Longer)), 0.5);
+        assert!(middle[0] > 0.99 && middle[1] < 0.7 && (middle[1] - middle[2]).abs() < 0.1, "{:?}", middle);
+    }
+
+    #[test]
+    fn oklch_hues_go_in_the_direction() {
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let blue = [0.0, 0.0, 1.0, 1.0];
+        let middle = |hue_interpolation| color_at(&gradient(&[(0.0, red), (1.0, blue)], Interpolation::Linear, ColorSpace::Oklch(hue_interpolation)), 0.5);
+        // red to blue the shorter way goes through purple, the longer way through green
+        let shorter = middle(HueInterpolation::Shorter);
+        let longer = middle(HueInterpolation::Longer);
+        assert!(shorter[0] > 0.5 && shorter[1] < 0.1 && shorter[2] > 0.5, "{:?}", shorter);
+        assert!(longer[1] > 0.4 && longer[0] < 0.1 && longer[2] < 0.1, "{:?}", longer);
+        assert_eq!(middle(HueInterpolation::Decreasing), shorter);
+        assert_eq!(middle(HueInterpolation::Increasing), longer);
+    }
+
+    #[test]
+    fn transparent_stops_are_premultiplied() {
+        let transparent_red = [1.0, 0.0, 0.0, 0.0];
+        let blue = [0.0, 0.0, 1.0, 1.0];
+        let middle = color_at(&gradient(&[(0.0, transparent_red), (1.0, blue)], Interpolation::Linear, ColorSpace::Oklab), 0.5);
+        assert!(middle[0] < 0.01 && middle[2] > 0.99 && (middle[3] - 0.5).abs() < 0.01, "{:?}", middle);
+    }
+}
@@ -13,24 +13,112 @@ uniform uint u_brush_type;
 uniform vec4 u_color;
 uniform vec2 gradient_start;
 uniform vec2 gradient_end;
-uniform vec4 colors[64];
-uniform float gradient_stops[64];
-uniform int gradient_stops_count;
+// radii of the circles around the start and the end of radial gradients
+uniform vec2 gradient_radii;
+// colours of the gradient from 0 to 1 in a single row
+uniform sampler2D gradient_ramp;
+// 0 pad, 1 repeat, 2 reflect
+uniform uint gradient_spread;
+// colours of the ramp, 0 sRGB, 1 premultiplied linear sRGB, 2 premultiplied OKLab
+uniform uint gradient_ramp_space;
 
 
+in vec2 gradient_coord;
 
 out vec4 out_color;
 
 
+float spread_position(in float t);
+float spread_position(in float t) {
+    if (gradient_spread == uint(1)) { // repeat
+        return fract(t);
+    } else if (gradient_spread == uint(2)) { // reflect
+        return 1.0 - abs(mod(t, 2.0) - 1.0);
+    }
+    // padded gradients extend the colours of the ends
+    return clamp(t, 0.0, 1.0);
+}
+
+// position along the radial gradient from the largest circle going through the point,
+// false where there is no such circle with a positive radius
+bool radial_position(in vec2 p, out float t);
+bool radial_position(in vec2 p, out float t) {
+    vec2 cd = gradient_end - gradient_start;
+    vec2 pd = p - gradient_start;
+    float r1 = gradient_radii.x;
+    float dr = gradient_radii.y - gradient_radii.x;
+
+    // circles around start + t * cd with radius r1 + t * dr going through the point give a * t^2 - 2 * b * t + c = 0
+    float a = dot(cd, cd) - dr * dr;
+    float b = dot(pd, cd) + r1 * dr;
+    float c = dot(pd, pd) - r1 * r1;
+
+    if (abs(a) <= 1e-6 * (dot(cd, cd) + dr * dr)) {
+        // circles touching each other in a single point, the equation is linear
+        t = c / (2.0 * b);
+        return !isinf(t) && !isnan(t) && r1 + t * dr >= 0.0;
+    }
+    float discriminant = b * b - a * c;
+    if (discriminant < 0.0) {
+        return false;
+    }
+    float root = sqrt(discriminant);
+    float t1 = (b + root) / a;
+    float t2 = (b - root) / a;
+    t = max(t1, t2);
+    if (r1 + t * dr >= 0.0) {
+        return true;
+    }
+    t = min(t1, t2);
+    return r1 + t * dr >= 0.0;
+}
+
+vec3 linear_to_srgb(in vec3 c);
+vec3 linear_to_srgb(in vec3 c) {
+    vec3 a = abs(c);
+    vec3 srgb = mix(1.055 * pow(a, vec3(1.0 / 2.4)) - 0.055, a * 12.92, lessThanEqual(a, vec3(0.0031308)));
+    return sign(c) * srgb;
+}
+
+vec3 oklab_to_linear_srgb(in vec3 lab);
+vec3 oklab_to_linear_srgb(in vec3 lab) {
+    float l = lab.x + 0.3963377774 * lab.y + 0.2158037573 * lab.z;
+    float m = lab.x - 0.1055613458 * lab.y - 0.0638541728 * lab.z;
+    float s = lab.x - 0.0894841775 * lab.y - 1.2914855480 * lab.z;
+    l = l * l * l;
+    m = m * m * m;
+    s = s * s * s;
+    return vec3(
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s
+    );
+}
+
+// sRGB colour not premultiplied of a colour of the ramp
+vec4 ramp_to_srgb(in vec4 c);
+vec4 ramp_to_srgb(in vec4 c) {
+    if (gradient_ramp_space == uint(0)) {
+        return c;
+    }
+    if (c.a <= 0.0) {
+        return vec4(0.0);
+    }
+    vec3 color = c.rgb / c.a;
+    if (gradient_ramp_space == uint(2)) {
+        color = oklab_to_linear_srgb(color);
+    }
+    return vec4(clamp(linear_to_srgb(color), 0.0, 1.0), c.a);
+}
+
 vec4 compute_gradient_color(in float t);
 vec4 compute_gradient_color(in float t) {
-    
-    vec4 result_color = mix(colors[0], colors[1], smoothstep( gradient_stops[0], gradient_stops[1], t ));
+    t = spread_position(t);
 
-    for (int i=2; i<gradient_stops_count; i++ ) {
-        result_color = mix(result_color, colors[i], smoothstep( gradient_stops[i - 1], gradient_stops[i], t ));
-    }
-    return result_color;
+    // every texel covers an equal part of the gradient, hard stops are on the borders between them
+    int size = textureSize(gradient_ramp, 0).x;
+    int texel = min(int(t * float(size)), size - 1);
+    return ramp_to_srgb(texelFetch(gradient_ramp, ivec2(texel, 0), 0));
 }
 
 void main() {
@@ -39,27 +127,26 @@ void main() {
     if (u_brush_type == uint(1)) { // solid color
         out_color = u_color;
     } else if (u_brush_type == uint(2)) { // linear gradient
-        float angle = atan(gradient_start.y - gradient_end.y, gradient_end.x - gradient_start.y);
+        float angle = atan(gradient_start.y - gradient_end.y, gradient_end.x - gradient_start.x);
         //vec2 dir = vec2(cos(angle), sin(angle));
         float start = gradient_start.x * cos(angle) - gradient_start.y * sin(angle);
         float dis = (gradient_end.x * cos(angle) - gradient_end.y * sin(angle)) - start;
         
-        vec2 coord = vec2(gl_FragCoord.x, u_res.y - gl_FragCoord.y);
+        vec2 coord = gradient_coord;
 
         float pos = coord.x * cos(angle) - coord.y * sin(angle);
         float t = (pos - start) / dis;
 
         out_color = compute_gradient_color(t);
     } else if (u_brush_type == uint(3)) {// radial_gradient 
-        vec2 coord = vec2(gl_FragCoord.x,u_res.y - gl_FragCoord.y);
-
-        coord = (coord - gradient_start) / (gradient_end - gradient_start);
-
-        float t = sqrt((coord.x * coord.x) + (coord.y * coord.y)) / 1.0;
-
-        out_color = compute_gradient_color(t);
+        float t;
+        if (radial_position(gradient_coord, t)) {
+            out_color = compute_gradient_color(t);
+        } else {
+            out_color = vec4(0.0);
+        }
     } else if (u_brush_type == uint(4)) {// conic_gradient 
-        vec2 coord = vec2(gl_FragCoord.x,u_res.y - gl_FragCoord.y);
+        vec2 coord = gradient_coord;
 
         float t = mod(atan(coord.y - gradient_start.y, coord.x - gradient_start.x), TAU) / TAU;
 
@@ -67,4 +154,6 @@ void main() {
     } else {
         out_color = vec4(1.0, 0.2, 0.2, 1.0);
     }
+    // blending and the canvas expect colours multiplied by their alpha
+    out_color.rgb *= out_color.a;
 }
\ No newline at end of file
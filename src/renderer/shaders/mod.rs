@@ -76,14 +76,17 @@ fn link_program(
 pub struct ShaderInfo {
     pub u_color : Option<WebGlUniformLocation>,
     pub u_res : Option<WebGlUniformLocation>,
+    pub u_point_size : Option<WebGlUniformLocation>,
     pub a_pos : u32,
     pub transform : Option<WebGlUniformLocation>,
     pub u_brush_type : Option<WebGlUniformLocation>,
+    pub gradient_transform : Option<WebGlUniformLocation>,
     pub gradient_start : Option<WebGlUniformLocation>,
     pub gradient_end : Option<WebGlUniformLocation>,
-    pub colors : Option<WebGlUniformLocation>,
-    pub gradient_stops : Option<WebGlUniformLocation>,
-    pub gradient_stops_count : Option<WebGlUniformLocation>,
+    pub gradient_radii : Option<WebGlUniformLocation>,
+    pub gradient_ramp : Option<WebGlUniformLocation>,
+    pub gradient_spread : Option<WebGlUniformLocation>,
+    pub gradient_ramp_space : Option<WebGlUniformLocation>,
 }
 
 pub fn create_shader_program(gl : &WebGl2RenderingContext) -> (WebGlProgram, ShaderInfo) {
@@ -96,14 +99,17 @@ pub fn create_shader_program(gl : &WebGl2RenderingContext) -> (WebGlProgram, Sha
     let shader_info = ShaderInfo{
         u_color : gl.get_uniform_location(&program, "u_color"),
         u_res : gl.get_uniform_location(&program, "u_res"),
+        u_point_size : gl.get_uniform_location(&program, "u_point_size"),
         a_pos : gl.get_attrib_location(&program, "a_pos") as u32,
         transform : gl.get_uniform_location(&program, "transform"),
         u_brush_type : gl.get_uniform_location(&program, "u_brush_type"),
+        gradient_transform : gl.get_uniform_location(&program, "gradient_transform"),
         gradient_start : gl.get_uniform_location(&program, "gradient_start"),
         gradient_end : gl.get_uniform_location(&program, "gradient_end"),
-        colors : gl.get_uniform_location(&program, "colors"),
-        gradient_stops : gl.get_uniform_location(&program, "gradient_stops"),
-        gradient_stops_count : gl.get_uniform_location(&program, "gradient_stops_count"),
+        gradient_radii : gl.get_uniform_location(&program, "gradient_radii"),
+        gradient_ramp : gl.get_uniform_location(&program, "gradient_ramp"),
+        gradient_spread : gl.get_uniform_location(&program, "gradient_spread"),
+        gradient_ramp_space : gl.get_uniform_location(&program, "gradient_ramp_space"),
     };
 
     (program, shader_info)
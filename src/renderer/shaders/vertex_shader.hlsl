@@ -4,8 +4,13 @@
 in vec2 a_pos;
 uniform vec2 u_res;
 uniform mat3 transform;
+// size of points in pixels, when drawing points
+uniform float u_point_size;
+// from the coordinates of the shape into the coordinates of the gradient
+uniform mat3 gradient_transform;
 
-in vec2 gradient_coord;
+// position in the gradient, interpolated so that gradients move together with the shapes
+out vec2 gradient_coord;
 
 vec2 normalizuj(in vec2 pos, in vec2 res);
 vec2 normalizuj(in vec2 pos, in vec2 res) {
@@ -26,5 +31,7 @@ void main() {
 
     
     gl_Position = vec4(normalizuj((vec3(a_pos,1) * transform).xy, u_res), 0, 1);
+    gl_PointSize = u_point_size;
+    gradient_coord = (vec3(a_pos, 1) * gradient_transform).xy;
 
 }
\ No newline at end of file
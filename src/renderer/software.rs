@@ -0,0 +1,473 @@
+use std::f32::consts::TAU;
+
+use crate::{matrix::Matrix3x3, point::Point};
+
+use super::{backend::Backend, ramp::{bake_ramp, ramp_to_srgb, sample_ramp, RampKey}, Brush, IndexedTriangles, Triangles, TrianglesMode};
+
+/**
+ * Backend filling a buffer of RGBA pixels in memory, without a browser. Brushes give the same colours
+ * as the fragment shader, pixels are sampled at their centres and composited like in WebGL with premultiplied alpha.
+ */
+pub struct SoftwareBackend {
+    width : u32,
+    height : u32,
+    /** 4 bytes per pixel premultiplied by alpha, rows from the top */
+    pixels : Vec<u8>,
+    /** pixels per canvas unit */
+    scale : f32,
+    transform : Matrix3x3,
+    brush : Brush,
+    /** from pixels into the coordinates of the brush, None when the brush paints nothing */
+    brush_space : Option<Matrix3x3>,
+    /** colours of the gradient of the brush, kept while gradients with the same key are set */
+    ramp : Vec<[f32; 4]>,
+    ramp_key : Option<RampKey>,
+}
+
+impl SoftwareBackend {
+    pub fn new(width : u32, height : u32) -> SoftwareBackend {
+        SoftwareBackend{
+            width,
+            height,
+            pixels : vec![0; width as usize * height as usize * 4],
+            scale : 1.0,
+            transform : Matrix3x3::identity(),
+            brush : Brush::Color(0.0, 0.0, 0.0, 0.0),
+            brush_space : Some(Matrix3x3::identity()),
+            ramp : Vec::new(),
+            ramp_key : None,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn into_pixels(self) -> Vec<u8> {
+        self.pixels
+    }
+
+    /**
+     * Colour of the pixel premultiplied by alpha, None outside of the buffer
+     */
+    pub fn pixel(&self, x : u32, y : u32) -> Option<[u8; 4]> {
+        if (x >= self.width || y >= self.height) {
+            return None;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        Some([self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]])
+    }
+
+    /**
+     * Sets the number of pixels per canvas unit, the buffer shows the canvas enlarged by it
+     */
+    pub fn set_scale(&mut self, scale : f32) {
+        self.scale = scale;
+        self.brush_space = brush_space(&self.brush, &self.transform, scale);
+    }
+
+    /**
+     * Changes the size of the buffer, its content is cleared
+     */
+    pub fn resize(&mut self, width : u32, height : u32) {
+        self.width = width;
+        self.height = height;
+        self.pixels = vec![0; width as usize * height as usize * 4];
+    }
+
+    fn fill_triangle(&mut self, a : Point, b : Point, c : Point) {
+        // clockwise on the screen, so that the edge functions are positive inside
+        let area = edge(&a, &b, &c);
+        if (area == 0.0 || !area.is_finite()) {
+            return;
+        }
+        let (a, b, c) = if (area > 0.0) {(a, b, c)} else {(a, c, b)};
+
+        let left = f64::max(a.x.min(b.x).min(c.x).floor(), 0.0) as u32;
+        let top = f64::max(a.y.min(b.y).min(c.y).floor(), 0.0) as u32;
+        let right = f64::min(a.x.max(b.x).max(c.x).ceil(), self.width as f64) as u32;
+        let bottom = f64::min(a.y.max(b.y).max(c.y).ceil(), self.height as f64) as u32;
+
+        for y in top..bottom {
+            for x in left..right {
+                let p = Point::new(x as f64 + 0.5, y as f64 + 0.5);
+                if (covers(&b, &c, &p) && covers(&c, &a, &p) && covers(&a, &b, &p)) {
+                    self.blend_pixel(x as i64, y as i64);
+                }
+            }
+        }
+    }
+
+    /**
+     * Composites the colour of the brush at the centre of the pixel over it, pixels outside of the buffer are skipped
+     */
+    fn blend_pixel(&mut self, x : i64, y : i64) {
+        if (x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64) {
+            return;
+        }
+        let Some(brush_space) = &self.brush_space else {
+            return;
+        };
+        let p = Point::new(x as f64 + 0.5, y as f64 + 0.5);
+        let color = premultiply(brush_color(&self.brush, &self.ramp, &brush_space.transform_point(&p)));
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let pixel = &mut self.pixels[i..i + 4];
+        let mut blended = [0.0; 4];
+        for c in 0..4 {
+            blended[c] = color[c] + pixel[c] as f32 / 255.0 * (1.0 - color[3]);
+        }
+        pixel.copy_from_slice(&to_bytes(blended));
+    }
+
+    /**
+     * Positions of the points in pixels, after the transform
+     */
+    fn to_pixels(&self, points : &[f32]) -> Vec<Point> {
+        points.chunks_exact(2)
+            .map(|v| self.transform.transform_point(&Point::new(v[0] as f64, v[1] as f64)) * self.scale as f64)
+            .collect()
+    }
+}
+
+impl Backend for SoftwareBackend {
+
+    type Mesh = IndexedTriangles;
+
+    fn create_mesh(&mut self, triangles : &IndexedTriangles) -> IndexedTriangles {
+        triangles.clone()
+    }
+
+    fn update_mesh(&mut self, mesh : &mut IndexedTriangles, triangles : &IndexedTriangles) {
+        *mesh = triangles.clone();
+    }
+
+    fn delete_mesh(&mut self, mesh : IndexedTriangles) {}
+
+    fn draw_mesh(&mut self, mesh : &IndexedTriangles) {
+        let points = self.to_pixels(&mesh.vertices);
+        for i in (0..mesh.indices.len()).step_by(3) {
+            self.fill_triangle(points[mesh.indices.get(i)], points[mesh.indices.get(i + 1)], points[mesh.indices.get(i + 2)]);
+        }
+    }
+
+    fn clear(&mut self, brush : &Brush) {
+        // the background is placed on the canvas, regardless of the transform
+        let Some(space) = brush_space(brush, &Matrix3x3::identity(), self.scale) else {
+            self.pixels.fill(0);
+            return;
+        };
+        let ramp = brush.gradient().map(bake_ramp).unwrap_or_default();
+        let width = self.width as usize;
+        for (i, pixel) in self.pixels.chunks_exact_mut(4).enumerate() {
+            let p = Point::new((i % width) as f64 + 0.5, (i / width) as f64 + 0.5);
+            pixel.copy_from_slice(&to_bytes(premultiply(brush_color(brush, &ramp, &space.transform_point(&p)))));
+        }
+    }
+
+    fn set_transform(&mut self, matrix : &Matrix3x3) {
+        self.transform = *matrix;
+        self.brush_space = brush_space(&self.brush, matrix, self.scale);
+    }
+
+    fn set_brush(&mut self, brush : &Brush) {
+        if let Some(gradient) = brush.gradient() {
+            let key = RampKey::new(gradient);
+            if (self.ramp_key.as_ref() != Some(&key)) {
+                self.ramp = bake_ramp(gradient);
+                self.ramp_key = Some(key);
+            }
+        }
+        self.brush = brush.clone();
+        self.brush_space = brush_space(brush, &self.transform, self.scale);
+    }
+
+    fn draw_triangles(&mut self, triangles : &Triangles) {
+        let points = self.to_pixels(&triangles.vertices);
+        if (points.len() < 3) {
+            return;
+        }
+        for i in 2..points.len() {
+            match triangles.mode {
+                TrianglesMode::Strip => self.fill_triangle(points[i - 2], points[i - 1], points[i]),
+                TrianglesMode::Fan => self.fill_triangle(points[0], points[i - 1], points[i]),
+            }
+        }
+    }
+
+    fn draw_lines(&mut self, points : &[f32]) {
+        let points = self.to_pixels(points);
+        for line in points.chunks_exact(2) {
+            let Some((a, b)) = clip_line(line[0], line[1], self.width as f64, self.height as f64) else {
+                continue;
+            };
+            // one pixel for each step along the longer axis
+            let steps = f64::max((b.x - a.x).abs(), (b.y - a.y).abs()).ceil().max(1.0);
+            if (!steps.is_finite()) {
+                continue;
+            }
+            for i in 0..=(steps as i64) {
+                let p = a + (b - a) * (i as f64 / steps);
+                self.blend_pixel(p.x.floor() as i64, p.y.floor() as i64);
+            }
+        }
+    }
+
+    fn draw_points(&mut self, points : &[f32], size : f32) {
+        let half = size as f64 / 2.0;
+        for p in self.to_pixels(points) {
+            for y in (p.y - half).round() as i64..(p.y + half).round() as i64 {
+                for x in (p.x - half).round() as i64..(p.x + half).round() as i64 {
+                    self.blend_pixel(x, y);
+                }
+            }
+        }
+    }
+}
+
+/**
+ * Part of the line inside the rectangle from zero to the width and the height, None if it misses the rectangle
+ */
+fn clip_line(a : Point, b : Point, width : f64, height : f64) -> Option<(Point, Point)> {
+    let d = b - a;
+    let (mut start, mut end) = (0.0f64, 1.0f64);
+    // distances of the point inside of each side of the rectangle, linear in the position along the line
+    for (p, q) in [(-d.x, a.x), (d.x, width - a.x), (-d.y, a.y), (d.y, height - a.y)] {
+        if (p == 0.0) {
+            if (q < 0.0) {
+                return None;
+            }
+        } else if (p < 0.0) {
+            start = start.max(q / p);
+        } else {
+            end = end.min(q / p);
+        }
+    }
+    if (start > end || !start.is_finite() || !end.is_finite()) {
+        return None;
+    }
+    Some((a + d * start, a + d * end))
+}
+
+/**
+ * Twice the signed area of the triangle, positive when it turns clockwise on the screen
+ */
+fn edge(a : &Point, b : &Point, p : &Point) -> f64 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+/**
+ * Whether the point is on the inner side of the edge. Points exactly on the edge belong to it only
+ * if it's a top or left edge, so pixels on an edge shared by two triangles are filled once.
+ */
+fn covers(a : &Point, b : &Point, p : &Point) -> bool {
+    let w = edge(a, b, p);
+    if (w != 0.0) {
+        return w > 0.0;
+    }
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    // top edges of clockwise triangles go to the right and left edges go up
+    (dy == 0.0 && dx > 0.0) || dy < 0.0
+}
+
+fn premultiply(color : [f32; 4]) -> [f32; 4] {
+    let [r, g, b, a] = color;
+    [r * a, g * a, b * a, a]
+}
+
+fn to_bytes(color : [f32; 4]) -> [u8; 4] {
+    color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+}
+
+/**
+ * Transform from pixels into the coordinates of the brush, through the coordinates of the shapes placed by the transform.
+ * None if the transform or the gradient collapse the plane, so that the gradient paints nothing.
+ */
+fn brush_space(brush : &Brush, transform : &Matrix3x3, scale : f32) -> Option<Matrix3x3> {
+    let Some(gradient) = brush.gradient() else {
+        return Some(Matrix3x3::identity());
+    };
+    let shape = transform.inverse()?.multiply(&Matrix3x3::scale(1.0 / scale, 1.0 / scale));
+    Some(gradient.inverse_transform()?.multiply(&shape))
+}
+
+/**
+ * Colour of the brush at the point given in the coordinates of the brush, same as computed by the fragment shader
+ * from the ramp of the gradient
+ */
+pub fn brush_color(brush : &Brush, ramp : &[[f32; 4]], p : &Point) -> [f32; 4] {
+    let (x, y) = (p.x as f32, p.y as f32);
+    let (gradient, t) = match brush {
+        Brush::Color(r, g, b, a) => return [*r, *g, *b, *a],
+        Brush::LinearGradient(gradient) => {
+            let angle = f32::atan2(gradient.y1 - gradient.y2, gradient.x2 - gradient.x1);
+            let (sin, cos) = angle.sin_cos();
+            let start = gradient.x1 * cos - gradient.y1 * sin;
+            let distance = (gradient.x2 * cos - gradient.y2 * sin) - start;
+            let position = x * cos - y * sin;
+            (gradient, (position - start) / distance)
+        },
+        Brush::RadialGradient(gradient) => match gradient.radial_position(x, y) {
+            Some(t) => (gradient, t),
+            None => return [0.0, 0.0, 0.0, 0.0],
+        },
+        Brush::ConicGradient(gradient) => {
+            let angle = f32::atan2(y - gradient.y1, x - gradient.x1);
+            (gradient, (angle - TAU * f32::floor(angle / TAU)) / TAU)
+        },
+    };
+    ramp_to_srgb(gradient.color_space, sample_ramp(ramp, gradient.spread.apply(t)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{data::{Document, Shape, ShapeStroke}, path::Path};
+    use crate::renderer::{image::{render_image, ImageOptions}, ColorSpace, FillRule, Gradient, GradientStop, Interpolation, LineJoin, Spread, StrokeStyle};
+
+    use super::*;
+
+    const BACKGROUND : [u8; 4] = [0, 0, 51, 255];
+
+    fn rect(x : f64, y : f64, width : f64, height : f64) -> Path {
+        let mut path = Path::new();
+        path.move_to(x, y);
+        path.line_to(x + width, y);
+        path.line_to(x + width, y + height);
+        path.line_to(x, y + height);
+        path.close();
+        path
+    }
+
+    fn render(width : f32, height : f32, shapes : Vec<(Path, Brush)>) -> SoftwareBackend {
+        let mut document = Document::new();
+        let root = document.root();
+        for (path, fill) in shapes {
+            document.add_shape(root, Shape{path, fill : Some(fill), fill_rule : FillRule::NonZero, stroke : None});
+        }
+        render_image(&document, &Matrix3x3::identity(), &ImageOptions::new(width, height))
+    }
+
+    #[test]
+    fn solid_rectangle() {
+        // edges go through pixel centres, the left and top ones are inside and the right and bottom ones outside
+        let image = render(8.0, 8.0, vec![(rect(1.5, 2.5, 3.0, 2.0), Brush::Color(1.0, 0.0, 0.0, 1.0))]);
+        for y in 0..8 {
+            for x in 0..8 {
+                let inside = (1..4).contains(&x) && (2..4).contains(&y);
+                let expected = if (inside) {[255, 0, 0, 255]} else {BACKGROUND};
+                assert_eq!(image.pixel(x, y), Some(expected), "pixel {} {}", x, y);
+            }
+        }
+        assert_eq!(image.pixel(8, 0), None);
+    }
+
+    #[test]
+    fn linear_gradient() {
+        let stops = vec![
+            GradientStop{position : 0.0, r : 0.0, g : 0.0, b : 0.0, a : 1.0},
+            GradientStop{position : 1.0, r : 1.0, g : 1.0, b : 1.0, a : 1.0},
+        ];
+        // from the centre of the first pixel to the centre of the ninth one
+        let gradient = Gradient{x1 : 0.5, y1 : 0.0, x2 : 8.5, y2 : 0.0, r1 : 0.0, r2 : 0.0, stops, spread : Spread::Pad,
+            interpolation : Interpolation::Linear, color_space : ColorSpace::Srgb, transform : None};
+        let image = render(10.0, 1.0, vec![(rect(0.0, 0.0, 10.0, 1.0), Brush::LinearGradient(gradient))]);
+        assert_eq!(image.pixel(0, 0), Some([0, 0, 0, 255]));
+        assert_eq!(image.pixel(8, 0), Some([255, 255, 255, 255]));
+        assert_eq!(image.pixel(9, 0), Some([255, 255, 255, 255]));
+        let [r, g, b, a] = image.pixel(4, 0).unwrap();
+        assert!((127..=128).contains(&r) && r == g && g == b && a == 255, "midpoint {:?}", [r, g, b, a]);
+    }
+
+    #[test]
+    fn repeated_linear_gradient() {
+        let stops = vec![
+            GradientStop{position : 0.0, r : 0.0, g : 0.0, b : 0.0, a : 1.0},
+            GradientStop{position : 1.0, r : 1.0, g : 1.0, b : 1.0, a : 1.0},
+        ];
+        // a period is four pixels long, starting at the centre of the first one
+        let gradient = Gradient{x1 : 0.5, y1 : 0.0, x2 : 4.5, y2 : 0.0, r1 : 0.0, r2 : 0.0, stops, spread : Spread::Repeat,
+            interpolation : Interpolation::Linear, color_space : ColorSpace::Srgb, transform : None};
+        let image = render(12.0, 1.0, vec![(rect(0.0, 0.0, 12.0, 1.0), Brush::LinearGradient(gradient))]);
+        for x in [0, 4, 8] {
+            assert_eq!(image.pixel(x, 0), Some([0, 0, 0, 255]), "start of the period at {}", x);
+            let [r, g, b, a] = image.pixel(x + 2, 0).unwrap();
+            assert!((127..=128).contains(&r) && r == g && g == b && a == 255, "middle of the period at {}: {:?}", x, [r, g, b, a]);
+        }
+        for x in 0..8 {
+            assert_eq!(image.pixel(x, 0), image.pixel(x + 4, 0), "pixel {}", x);
+        }
+    }
+
+    #[test]
+    fn hard_stop_on_a_wide_gradient() {
+        let red = GradientStop{position : 0.5, r : 1.0, g : 0.0, b : 0.0, a : 1.0};
+        let blue = GradientStop{position : 0.5, r : 0.0, g : 0.0, b : 1.0, a : 1.0};
+        let stops = vec![GradientStop{position : 0.0, ..red.clone()}, red, blue.clone(), GradientStop{position : 1.0, ..blue}];
+        let gradient = Gradient{x1 : 0.0, y1 : 0.0, x2 : 2048.0, y2 : 0.0, r1 : 0.0, r2 : 0.0, stops, spread : Spread::Pad,
+            interpolation : Interpolation::Linear, color_space : ColorSpace::Srgb, transform : None};
+        let image = render(2048.0, 1.0, vec![(rect(0.0, 0.0, 2048.0, 1.0), Brush::LinearGradient(gradient))]);
+        assert_eq!(image.pixel(1023, 0), Some([255, 0, 0, 255]));
+        assert_eq!(image.pixel(1024, 0), Some([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn half_transparent_fill() {
+        let image = render(2.0, 2.0, vec![(rect(0.0, 0.0, 1.0, 1.0), Brush::Color(1.0, 0.0, 0.0, 0.5))]);
+        // half of the red over the background darkened by half
+        let [r, g, b, a] = image.pixel(0, 0).unwrap();
+        assert!((127..=128).contains(&r) && g == 0 && (25..=26).contains(&b) && a == 255, "blended {:?}", [r, g, b, a]);
+        assert_eq!(image.pixel(1, 1), Some(BACKGROUND));
+
+        let mut options = ImageOptions::new(2.0, 2.0);
+        options.background = None;
+        let mut document = Document::new();
+        let root = document.root();
+        document.add_shape(root, Shape{path : rect(0.0, 0.0, 1.0, 1.0), fill : Some(Brush::Color(1.0, 0.0, 0.0, 0.5)), fill_rule : FillRule::NonZero, stroke : None});
+        // over transparency the pixel keeps the colour premultiplied by its alpha
+        let [r, g, b, a] = render_image(&document, &Matrix3x3::identity(), &options).pixel(0, 0).unwrap();
+        assert!((127..=128).contains(&r) && g == 0 && b == 0 && r == a, "premultiplied {:?}", [r, g, b, a]);
+    }
+
+    #[test]
+    fn translucent_stroke_is_blended_once() {
+        let mut corner = Path::new();
+        corner.move_to(5.0, 5.0);
+        corner.line_to(25.0, 5.0);
+        corner.line_to(25.0, 25.0);
+        // segments crossing each other overlap away from the joins
+        let mut crossing = Path::new();
+        crossing.move_to(5.0, 20.0);
+        crossing.line_to(35.0, 20.0);
+        crossing.line_to(20.0, 35.0);
+        crossing.line_to(20.0, 5.0);
+
+        let mut options = ImageOptions::new(40.0, 40.0);
+        options.background = None;
+        for path in [corner, crossing] {
+            for join in [LineJoin::Miter, LineJoin::Round, LineJoin::Bevel] {
+                let mut document = Document::new();
+                let root = document.root();
+                let stroke = ShapeStroke{brush : Brush::Color(1.0, 0.0, 0.0, 0.5), style : StrokeStyle{join, ..StrokeStyle::new(10.0)}};
+                document.add_shape(root, Shape{path : path.clone(), fill : None, fill_rule : FillRule::NonZero, stroke : Some(stroke)});
+                let image = render_image(&document, &Matrix3x3::identity(), &options);
+
+                let mut covered = 0;
+                for y in 0..40 {
+                    for x in 0..40 {
+                        let [_, _, _, a] = image.pixel(x, y).unwrap();
+                        assert!(a == 0 || (127..=128).contains(&a), "alpha {} at {} {}", a, x, y);
+                        covered += (a > 0) as u32;
+                    }
+                }
+                assert!(covered > 300);
+            }
+        }
+    }
+}
@@ -0,0 +1,429 @@
+use std::f64::consts::PI;
+
+use crate::{path::Path, point::Point, renderer::P};
+
+use super::{tesselation::{normalize_contours, tesselate_polygon}, FillRule, LineCap, LineJoin, StrokeStyle, Triangles};
+
+/**
+ * Collects the outlines of a stroke, which wind so that they enclose a positive area. Where parts of the stroke
+ * overlap the windings add up, so filling the outlines with the non-zero rule covers every pixel once.
+ */
+struct StrokeBuilder {
+    contours : Vec<Vec<P>>,
+}
+
+impl StrokeBuilder {
+
+    fn new() -> StrokeBuilder {
+        StrokeBuilder{contours : Vec::new()}
+    }
+
+    fn add_contour(&mut self, points : &[Point]) {
+        self.contours.push(points.iter().map(|p| P::new(p.x as f32, p.y as f32)).collect());
+    }
+
+    fn build(self) -> Vec<Triangles> {
+        normalize_contours(&self.contours, FillRule::NonZero).iter().flat_map(tesselate_polygon).collect()
+    }
+}
+
+/**
+ * Strokes all subpaths of the path, closed ones get joins instead of caps at their ends
+ */
+pub fn stroke_path(path : &Path, style : &StrokeStyle) -> Vec<Triangles> {
+    let subpaths = path.flatten();
+    let length : f64 = subpaths.iter().map(|subpath| polyline_length(&subpath.points, subpath.closed)).sum();
+    let style = if (style.is_dashed() && !has_drawable_dashes(style, length, path.tolerance())) {
+        StrokeStyle{dash_array : Vec::new(), ..style.clone()}
+    } else {
+        style.clone()
+    };
+
+    let mut builder = StrokeBuilder::new();
+    for subpath in subpaths {
+        stroke_polyline(&subpath.points, subpath.closed, &style, path.tolerance(), &mut builder);
+    }
+    builder.build()
+}
+
+/**
+ * Most dashes a stroke is cut into, denser patterns are drawn solid
+ */
+const MAX_DASHES : f64 = 100_000.0;
+
+/**
+ * Whether cutting a stroke of the length into dashes gives a reasonable number of them. Patterns shorter than
+ * the tolerance look solid anyway, and patterns too dense would take forever to cut.
+ */
+fn has_drawable_dashes(style : &StrokeStyle, length : f64, tolerance : f64) -> bool {
+    let total : f64 = style.dash_array.iter().map(|d| *d as f64).sum();
+    total >= tolerance && length / total * style.dash_array.len() as f64 <= MAX_DASHES
+}
+
+fn polyline_length(points : &Vec<Point>, closed : bool) -> f64 {
+    let open : f64 = points.windows(2).map(|pair| pair[0].distance(&pair[1])).sum();
+    match (closed, points.first(), points.last()) {
+        (true, Some(first), Some(last)) => open + last.distance(first),
+        _ => open,
+    }
+}
+
+/**
+ * Strokes a single polyline, round joins and caps are approximated within the tolerance.
+ * Dashed strokes are cut into open polylines first, every one of them gets its own caps.
+ */
+fn stroke_polyline(points : &Vec<Point>, closed : bool, style : &StrokeStyle, tolerance : f64, builder : &mut StrokeBuilder) {
+    if (!style.is_dashed() || points.len() < 2) {
+        stroke_solid_polyline(points, closed, style, tolerance, builder);
+        return;
+    }
+
+    let dashes : Vec<f64> = style.dash_array.iter().map(|d| *d as f64).collect();
+    for dash in dash_polyline(points, closed, &dashes, style.dash_offset as f64) {
+        stroke_solid_polyline(&dash, false, style, tolerance, builder);
+    }
+}
+
+/**
+ * Cuts polyline into dashes following the pattern of alternating dash and gap lengths, starting at offset within the pattern
+ */
+fn dash_polyline(points : &Vec<Point>, closed : bool, dash_array : &Vec<f64>, offset : f64) -> Vec<Vec<Point>> {
+    // pattern of odd length is repeated to get pairs of dashes and gaps
+    let mut pattern = dash_array.clone();
+    if (pattern.len() % 2 == 1) {
+        pattern.extend(dash_array.iter());
+    }
+    let total : f64 = pattern.iter().sum();
+
+    // entries the offset goes past are skipped at most once around the pattern, zero length dashes
+    // are kept where the offset falls right on them so that dotted strokes start with a dot
+    let mut index = 0;
+    let mut remaining = offset.rem_euclid(total);
+    let mut skipped = 0;
+    while (skipped < pattern.len() && (remaining > pattern[index] || (pattern[index] > 0.0 && remaining == pattern[index]))) {
+        remaining -= pattern[index];
+        index = (index + 1) % pattern.len();
+        skipped += 1;
+    }
+    remaining = (pattern[index] - remaining).max(0.0);
+
+    let mut dashes : Vec<Vec<Point>> = Vec::new();
+    let mut current : Vec<Point> = Vec::new();
+    if (index % 2 == 0) {
+        current.push(points[0]);
+    }
+
+    let segments_count = if (closed) {points.len()} else {points.len() - 1};
+    for i in 0..segments_count {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        let length = a.distance(&b);
+        let mut position = 0.0;
+
+        while (length - position > remaining) {
+            position += remaining;
+            let p = a + (b - a) * (position / length);
+            current.push(p);
+            if (index % 2 == 0) {
+                dashes.push(std::mem::take(&mut current));
+            }
+            index = (index + 1) % pattern.len();
+            remaining = pattern[index];
+        }
+
+        remaining -= length - position;
+        if (index % 2 == 0) {
+            current.push(b);
+        }
+    }
+
+    if (index % 2 == 0 && !current.is_empty()) {
+        dashes.push(current);
+    }
+
+    dashes
+}
+
+/**
+ * Outlines a polyline by the offsets of both its sides. Outer sides of corners get the join,
+ * inner sides go through the corner itself, which makes the outline wind once around every segment and join.
+ */
+fn stroke_solid_polyline(points : &Vec<Point>, closed : bool, style : &StrokeStyle, tolerance : f64, builder : &mut StrokeBuilder) {
+    let half_width = style.width as f64 / 2.0;
+    if (half_width <= 0.0) {
+        return;
+    }
+
+    let mut points : Vec<Point> = points.clone();
+    points.dedup_by(|a, b| a == b);
+    if (closed && points.len() > 1 && points[0] == points[points.len() - 1]) {
+        points.pop();
+    }
+
+    if (points.len() == 1) {
+        // zero length subpaths are drawn only with their caps
+        if (!closed) {
+            add_dot(&points[0], half_width, &style.cap, tolerance, builder);
+        }
+        return;
+    }
+    if (points.len() < 2) {
+        return;
+    }
+
+    if (closed) {
+        let mut left = offset_side(&points, true, 1.0, half_width, style, tolerance);
+        left.reverse();
+        builder.add_contour(&offset_side(&points, true, -1.0, half_width, style, tolerance));
+        builder.add_contour(&left);
+        return;
+    }
+
+    let last = points.len() - 1;
+    let start_direction = (points[1] - points[0]).normalized();
+    let end_direction = (points[last] - points[last - 1]).normalized();
+    if (style.cap == LineCap::Square) {
+        points[0] = points[0] - start_direction * half_width;
+        points[last] = points[last] + end_direction * half_width;
+    }
+
+    let mut outline = offset_side(&points, false, -1.0, half_width, style, tolerance);
+    if (style.cap == LineCap::Round) {
+        outline.extend(cap_points(&points[last], &end_direction, half_width, tolerance));
+    }
+    let mut left = offset_side(&points, false, 1.0, half_width, style, tolerance);
+    left.reverse();
+    outline.extend(left);
+    if (style.cap == LineCap::Round) {
+        outline.extend(cap_points(&points[0], &-start_direction, half_width, tolerance));
+    }
+    builder.add_contour(&outline);
+}
+
+fn get_normal(direction : &Point) -> Point {
+    Point::new(-direction.y, direction.x)
+}
+
+/**
+ * Offset of the polyline to one side, 1 for the side of its normals and -1 for the other one
+ */
+fn offset_side(points : &Vec<Point>, closed : bool, side : f64, half_width : f64, style : &StrokeStyle, tolerance : f64) -> Vec<Point> {
+    let segments_count = if (closed) {points.len()} else {points.len() - 1};
+    let mut offset = Vec::with_capacity(segments_count * 2);
+
+    for i in 0..segments_count {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        let direction = (b - a).normalized();
+        let normal = get_normal(&direction) * (side * half_width);
+        offset.push(a + normal);
+        offset.push(b + normal);
+
+        let has_join = closed || i + 1 < segments_count;
+        if (has_join) {
+            let c = points[(i + 2) % points.len()];
+            add_join(&b, &direction, &(c - b).normalized(), side, half_width, style, tolerance, &mut offset);
+        }
+    }
+
+    offset
+}
+
+/**
+ * Adds the points between the offsets of two segments meeting at point p. On the outer side of the corner
+ * these fill the gap by the join, on the inner side the offsets are connected through p.
+ */
+fn add_join(p : &Point, incoming : &Point, outgoing : &Point, side : f64, half_width : f64, style : &StrokeStyle, tolerance : f64, offset : &mut Vec<Point>) {
+    let cross = incoming.x * outgoing.y - incoming.y * outgoing.x;
+    let dot = incoming.dot(outgoing);
+
+    if (cross.abs() < 1e-9 && dot > 0.0) {
+        // straight continuation
+        return;
+    }
+
+    // the outer side of the corner is opposite to the direction of the turn
+    let outer_side = if (cross > 0.0) {-1.0} else {1.0};
+    if (side != outer_side) {
+        offset.push(*p);
+        return;
+    }
+
+    let n1 = get_normal(incoming) * side;
+    let n2 = get_normal(outgoing) * side;
+
+    match style.join {
+        LineJoin::Miter => {
+            // ratio of the miter length to the stroke width is 1 / cos(turn / 2)
+            let cos_half_turn = f64::sqrt((1.0 + dot) / 2.0);
+            if (cos_half_turn > 0.0 && 1.0 / cos_half_turn <= style.miter_limit as f64) {
+                offset.push(*p + (n1 + n2).normalized() * (half_width / cos_half_turn));
+            }
+        },
+        LineJoin::Bevel => (),
+        LineJoin::Round => {
+            let start = n1.direction();
+            let mut sweep = n2.direction() - start;
+            if (sweep > PI) {
+                sweep -= 2.0 * PI;
+            } else if (sweep < -PI) {
+                sweep += 2.0 * PI;
+            }
+            let arc = arc_points(p, half_width, start, sweep, tolerance);
+            offset.extend_from_slice(&arc[1..arc.len() - 1]);
+        }
+    }
+}
+
+/**
+ * Half circle around point p bulging in the direction, from the side opposite to its normal to the side of it
+ */
+fn cap_points(p : &Point, direction : &Point, half_width : f64, tolerance : f64) -> Vec<Point> {
+    let start = (-get_normal(direction)).direction();
+    let arc = arc_points(p, half_width, start, PI, tolerance);
+    arc[1..arc.len() - 1].to_vec()
+}
+
+/**
+ * Cap of a subpath with no length, which is either a circle or a square
+ */
+fn add_dot(p : &Point, half_width : f64, cap : &LineCap, tolerance : f64, builder : &mut StrokeBuilder) {
+    match cap {
+        LineCap::Butt => (),
+        LineCap::Round => {
+            // the last point of the circle repeats the first one
+            let mut points = arc_points(p, half_width, 0.0, 2.0 * PI, tolerance);
+            points.pop();
+            builder.add_contour(&points);
+        },
+        LineCap::Square => builder.add_contour(&[
+            *p + Point::new(-half_width, -half_width),
+            *p + Point::new(half_width, -half_width),
+            *p + Point::new(half_width, half_width),
+            *p + Point::new(-half_width, half_width),
+        ]),
+    }
+}
+
+/**
+ * Points on a circular arc, including both ends
+ */
+fn arc_points(center : &Point, radius : f64, start : f64, sweep : f64, tolerance : f64) -> Vec<Point> {
+    let step = if (tolerance < radius) {2.0 * f64::acos(1.0 - tolerance / radius)} else {PI / 2.0};
+    let count = usize::max(1, (sweep.abs() / step).ceil() as usize);
+
+    (0..=count).map(|i| {
+        let angle = start + sweep * i as f64 / count as f64;
+        *center + Point::new(angle.cos(), angle.sin()) * radius
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::renderer::tesselation::index_triangles;
+
+    use super::*;
+
+    fn polyline_path(points : &[(f64, f64)]) -> Path {
+        let mut path = Path::new();
+        path.move_to(points[0].0, points[0].1);
+        for (x, y) in &points[1..] {
+            path.line_to(*x, *y);
+        }
+        path
+    }
+
+    fn vertices(triangles : &[Triangles]) -> Vec<Point> {
+        triangles.iter().flat_map(|t| t.vertices.chunks_exact(2).map(|v| Point::new(v[0] as f64, v[1] as f64))).collect()
+    }
+
+    /** how far the stroke of the corner at (10, 0) turning from +x to +y reaches past it diagonally outwards */
+    fn corner_reach(join : LineJoin) -> f64 {
+        let style = StrokeStyle{join, ..StrokeStyle::new(2.0)};
+        let triangles = stroke_path(&polyline_path(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)]), &style);
+        vertices(&triangles).iter().map(|p| ((p.x - 10.0) - p.y) / 2f64.sqrt()).fold(f64::NEG_INFINITY, f64::max)
+    }
+
+    #[test]
+    fn joins() {
+        // vertices of merged outlines are snapped to a grid of 1 / 1024
+        assert!((corner_reach(LineJoin::Miter) - 2f64.sqrt()).abs() < 1e-3);
+        assert!((corner_reach(LineJoin::Round) - 1.0).abs() < 1e-3);
+        assert!((corner_reach(LineJoin::Bevel) - 1.0 / 2f64.sqrt()).abs() < 1e-3);
+    }
+
+    #[test]
+    fn miter_limit() {
+        // the segments meet at about 11 degrees, so the miter is about 10 times the stroke width
+        let path = polyline_path(&[(0.0, 0.0), (10.0, 0.0), (0.0, 2.0)]);
+        let reach = |miter_limit : f32| {
+            let style = StrokeStyle{miter_limit, ..StrokeStyle::new(2.0)};
+            vertices(&stroke_path(&path, &style)).iter().map(|p| p.x).fold(f64::NEG_INFINITY, f64::max)
+        };
+        assert!(reach(20.0) > 19.0);
+        assert!(reach(4.0) < 10.5);
+    }
+
+    #[test]
+    fn dashes() {
+        let path = polyline_path(&[(0.0, 0.0), (30.0, 0.0)]);
+        let style = StrokeStyle{dash_array : vec![10.0, 5.0], ..StrokeStyle::new(2.0)};
+        let triangles = stroke_path(&path, &style);
+        let points = vertices(&triangles);
+        assert!(points.iter().all(|p| p.x <= 10.0 || (p.x >= 15.0 && p.x <= 25.0)));
+
+        let indexed = index_triangles(&triangles);
+        let vertex = |i : usize| Point::new(indexed.vertices[indexed.indices.get(i) * 2] as f64, indexed.vertices[indexed.indices.get(i) * 2 + 1] as f64);
+        let area : f64 = (0..indexed.indices.len() / 3).map(|t| {
+            let (a, b, c) = (vertex(t * 3), vertex(t * 3 + 1), vertex(t * 3 + 2));
+            ((b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y)).abs() / 2.0
+        }).sum();
+        assert!((area - 40.0).abs() < 1e-6);
+    }
+
+    fn distance_to_polyline(p : &Point, points : &Vec<Point>) -> f64 {
+        points.windows(2).map(|pair| {
+            let (a, b) = (pair[0], pair[1]);
+            let ab = b - a;
+            let t = (((p.x - a.x) * ab.x + (p.y - a.y) * ab.y) / (ab.x * ab.x + ab.y * ab.y)).clamp(0.0, 1.0);
+            p.distance(&(a + ab * t))
+        }).fold(f64::INFINITY, f64::min)
+    }
+
+    #[test]
+    fn dots_with_offset_stay_on_the_polyline() {
+        let points = vec![Point::new(0.0, 0.0), Point::new(30.0, 0.0), Point::new(30.0, 30.0)];
+        let dashes = dash_polyline(&points, false, &vec![0.0, 10.0], 5.0);
+        assert_eq!(dashes.len(), 6);
+        for dash in &dashes {
+            for p in dash {
+                assert!(distance_to_polyline(p, &points) < 1e-9, "({}, {}) is off the polyline", p.x, p.y);
+            }
+        }
+        // dots every 10 starting 5 into the polyline
+        assert!(dashes[0][0].distance(&Point::new(5.0, 0.0)) < 1e-9);
+        assert!(dashes[5][0].distance(&Point::new(30.0, 25.0)) < 1e-9);
+    }
+
+    #[test]
+    fn dense_dashes_are_drawn_solid() {
+        let path = polyline_path(&[(0.0, 0.0), (1000.0, 0.0)]);
+        let solid = stroke_path(&path, &StrokeStyle::new(2.0));
+        for dash_array in [vec![1e-6], vec![0.01, 0.01], vec![1e-30, 0.0]] {
+            let style = StrokeStyle{dash_array, ..StrokeStyle::new(2.0)};
+            let dashed = stroke_path(&path, &style);
+            assert_eq!(vertices(&dashed).len(), vertices(&solid).len());
+        }
+        // a dash pattern of the tolerance is still cut into dashes
+        let style = StrokeStyle{dash_array : vec![0.125, 0.125], ..StrokeStyle::new(2.0)};
+        assert!(vertices(&stroke_path(&path, &style)).len() >= 4000 * 4);
+    }
+
+    #[test]
+    fn dots_without_offset_start_at_the_first_point() {
+        let points = vec![Point::new(0.0, 0.0), Point::new(25.0, 0.0)];
+        let dashes = dash_polyline(&points, false, &vec![0.0, 10.0], 0.0);
+        let starts : Vec<f64> = dashes.iter().map(|dash| dash[0].x).collect();
+        assert_eq!(starts, vec![0.0, 10.0, 20.0]);
+    }
+}
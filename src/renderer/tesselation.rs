@@ -1,6 +1,8 @@
-use crate::{base::log, renderer::{TrianglesMode, P}, Orientation};
+use std::collections::HashMap;
 
-use super::{Polygon, Triangles};
+use crate::{base::log, path::Path, renderer::{TrianglesMode, P}, Orientation};
+
+use super::{FillRule, IndexedTriangles, Indices, Polygon, Triangles};
 
 struct Pos {
     index : usize,
@@ -11,9 +13,9 @@ struct Index {
     pub index : usize
 }
 
-fn get_next(polygon : &Polygon, addresses: &mut Vec<usize>, index : &mut Index) -> Pos {
+fn get_next(points : &Vec<P>, addresses: &mut Vec<usize>, index : &mut Index) -> Pos {
     index.index = addresses[index.index];
-    Pos{index : index.index, point : polygon.points[index.index]}
+    Pos{index : index.index, point : points[index.index]}
 }
 
 
@@ -26,7 +28,8 @@ fn is_convex(a : &P, b : &P, c : &P, polygon_orientation : &Orientation) -> bool
     match polygon_orientation {
         Orientation::Clockwise => orientation == Orientation::Clockwise,
         Orientation::CounterClockwise => orientation == Orientation::CounterClockwise,
-        Orientation::Colinear => panic!("Polygon cannot be collinear!")
+        // polygon without area has no convex angles
+        Orientation::Colinear => false
     }
 }
 
@@ -43,6 +46,33 @@ fn get_orientation(a : &P, b : &P, c : &P) -> Orientation
     }
 } 
 
+/**
+ * Signed area of a closed contour, positive for clockwise contours (in screen coordinates)
+ */
+pub fn signed_area(points : &Vec<P>) -> f32 {
+    let mut area = 0.0;
+    for i in 0..points.len() {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        area += a.x * b.y - b.x * a.y;
+    }
+    area / 2.0
+}
+
+/**
+ * Winding of a closed contour computed from its signed area, Colinear if the contour has no area
+ */
+pub fn contour_orientation(points : &Vec<P>) -> Orientation {
+    let area = signed_area(points);
+    if (area > 0.0) {
+        Orientation::Clockwise
+    } else if (area < 0.0) {
+        Orientation::CounterClockwise
+    } else {
+        Orientation::Colinear
+    }
+}
+
 fn do_intersect(a : &P, b : &P, c : &P, d : &P) -> bool {
     let o1 = get_orientation(a, b, c);
     let o2 = get_orientation(a, b, d);
@@ -54,81 +84,45 @@ fn do_intersect(a : &P, b : &P, c : &P, d : &P) -> bool {
     return o1 != o2 && o3 != o4
 }
 
-fn is_visible(a : &Pos, b : &Pos, c : &Pos, polygon_orientation : &Orientation, addresses: &Vec<usize>, polygon_points : &Vec<P>, vertices_left : usize) -> bool {
+/**
+ * Checks whether point p lies inside triangle abc or on its edges
+ */
+fn is_in_triangle(a : &P, b : &P, c : &P, p : &P) -> bool {
+    let o1 = get_orientation(a, b, p);
+    let o2 = get_orientation(b, c, p);
+    let o3 = get_orientation(c, a, p);
+
+    let has_cw = o1 == Orientation::Clockwise || o2 == Orientation::Clockwise || o3 == Orientation::Clockwise;
+    let has_ccw = o1 == Orientation::CounterClockwise || o2 == Orientation::CounterClockwise || o3 == Orientation::CounterClockwise;
+
+    !(has_cw && has_ccw)
+}
+
+fn is_same(a : &P, b : &P) -> bool {
+    a.x == b.x && a.y == b.y
+}
 
-    //log(format!("Compute is visible for {}, {}, {}", a.index, b.index, c.index).as_str());
+/**
+ * Checks whether triangle abc is an ear: the angle at b is convex and no other vertex of the polygon lies within the triangle.
+ * Vertices sharing position with a, b or c are skipped, as bridged holes produce such duplicates.
+ */
+fn is_ear(a : &Pos, b : &Pos, c : &Pos, polygon_orientation : &Orientation, addresses: &Vec<usize>, points : &Vec<P>) -> bool {
 
     if (!is_convex(&a.point, &b.point, &c.point, polygon_orientation)) {
-        //log("111 Not convex");
         return false
-    } else {
-        let test = Orientation::Clockwise == Orientation::Clockwise;
-        //log("BEGIN");
-        // look for other line segemnts
-
-        let mut index = addresses[c.index];
-        let mut from = polygon_points[index];
-        //log(format!("From {}", index).as_str());
-        let mut o1 = get_orientation(&a.point, &b.point, &from);
-
-        // check if new line is not within the angle (b, c, a)
-        if is_convex(&b.point, &c.point, &from, polygon_orientation)
-            && is_convex(&from, &c.point, &a.point, polygon_orientation)  {
-            //log("Not visible - not convex at the end");
-            return false
-        }
-
-        let mut index = addresses[index];
-        //log(format!("To   {}", index).as_str());
-        let mut to = polygon_points[index];
+    }
 
-        // test whether any line intersects the new one
-        if (vertices_left > 5) {
-            loop {
-                // check whether the line from, to intersects with the proposed line a,c
-                let o2 = get_orientation(&a.point, &b.point, &to);
-                
-                if (o1 != o2)  {
-                    let o3 = get_orientation(&from, &to, &a.point);
-                    let o4 = get_orientation(&from, &to, &b.point);
-
-                    if (o3 != o4) {
-                        //disregard lines that only touch but not inersect
-                        if (o1 != Orientation::Colinear && o2 != Orientation::Colinear && o3 != Orientation::Colinear && o4 != Orientation::Colinear) 
-                        {
-                            return false;
-                        }
-                    }
-                }
-                o1 = o2;
-
-                // if (do_intersect(&a.point, &c.point, &from, &to)) {
-                //     //log("Not visible - przecinają się");
-                //     return false;
-                // }
-                index = addresses[index];
-                //log(format!("Ind  {}", index).as_str());
-                if (index == a.index) {
-                    //log("koniec");
-                    break;
-                }
-                
-                from = to;
-                to = polygon_points[index];
-            }
-        } else {
-            //log("Less than 5");
-        }
-        //TODO: this is computed twice - now, and later in inner loop
-        // check if new line is not within the angle (b, c, a)
-        if is_convex(&b.point, &a.point, &to, polygon_orientation)
-            && is_convex(&to, &a.point, &c.point, polygon_orientation)  {
-            // //log(format!("Not visible - not convex at the end ({})", index).as_str());
+    let mut index = addresses[c.index];
+    while (index != a.index) {
+        let p = points[index];
+        if (!is_same(&p, &a.point) && !is_same(&p, &b.point) && !is_same(&p, &c.point)
+            && is_in_triangle(&a.point, &b.point, &c.point, &p)) {
             return false
         }
-        
-        return true
+        index = addresses[index];
     }
+
+    return true
 }
 
 fn is_in(a : P, b : P, c : P, d : P) -> bool{
@@ -158,32 +152,62 @@ fn get_line_intersection_point(a : P, b : P, c : P, d : P) -> Option<P> {
 
 
 /**
- * Tesselates polygon using ear clipping method
+ * Tesselates polygon using ear clipping method. Holes are bridged into the outer contour first,
+ * so that the whole shape can be clipped as a single ring.
+ * The winding of the outer contour is detected from its area, so it doesn't have to match `polygon.orientation`.
  */
 pub fn tesselate_polygon(polygon : &Polygon) -> Vec<Triangles> {
 
+    let orientation = contour_orientation(&polygon.points);
+    if (orientation == Orientation::Colinear) {
+        return Vec::new();
+    }
+
+    if (polygon.holes.is_empty()) {
+        tesselate_ring(&polygon.points, &orientation)
+    } else {
+        let ring = bridge_holes(&polygon.points, &polygon.holes, &orientation);
+        tesselate_ring(&ring, &orientation)
+    }
+}
+
+fn tesselate_ring(points : &Vec<P>, orientation : &Orientation) -> Vec<Triangles> {
 
-    let mut vertices_left = polygon.points.len();
-    let mut index = Index{index: 0};
-    let mut addresses : Vec<usize> = Vec::with_capacity(polygon.points.len());
     let mut strips : Vec<Triangles> = Vec::new();
-    let mut current : Pos;
+    if (points.len() < 3) {
+        return strips;
+    }
+
+    let mut vertices_left = points.len();
+    let mut index = Index{index: 0};
+    let mut addresses : Vec<usize> = Vec::with_capacity(points.len());
 
-    for i in 1..polygon.points.len() {addresses.push(i)};
+    for i in 1..points.len() {addresses.push(i)};
     addresses.push(0);
 
 
-    let mut a = get_next(&polygon, &mut addresses, &mut index);
-    let mut b = get_next(&polygon, &mut addresses, &mut index);
-    let mut c = get_next(&polygon, &mut addresses, &mut index);
+    let mut a = get_next(points, &mut addresses, &mut index);
+    let mut b = get_next(points, &mut addresses, &mut index);
+    let mut c = get_next(points, &mut addresses, &mut index);
 
 
     let mut count = 0;
     'outer : loop {
-        let visible = is_visible(&a, &b, &c, &polygon.orientation, &addresses, &polygon.points, vertices_left);
-        
-        if visible { // found possible triangle
-            //TODO: check przecięcie z innymi liniami
+        if (vertices_left < 3) {
+            break;
+        }
+
+        if (get_orientation(&a.point, &b.point, &c.point) == Orientation::Colinear) {
+            // degenerate corner (duplicate point or a bridge spike), drop it without producing a triangle
+            addresses[a.index] = c.index;
+            vertices_left -= 1;
+            count = 0;
+            b = c;
+            c = get_next(points, &mut addresses, &mut index);
+            continue;
+        }
+
+        if is_ear(&a, &b, &c, orientation, &addresses, points) { // found possible triangle
             //log(format!("Visible {}, {}, {}", a.index, b.index, c.index).as_str());
             let mut strip : Vec<f32> = Vec::new();
             strip.push(a.point.x());
@@ -196,27 +220,22 @@ pub fn tesselate_polygon(polygon : &Polygon) -> Vec<Triangles> {
                 strip.push(c.point.x());
                 strip.push(c.point.y());
 
-                
+                addresses[a.index] = c.index;
                 vertices_left -= 1;
                 b = c;
-                c = get_next(&polygon, &mut addresses, &mut index);
-
-                if (vertices_left < 4) {
+                c = get_next(points, &mut addresses, &mut index);
 
-                    strip.push(c.point.x());
-                    strip.push(c.point.y());
+                if (vertices_left < 3) {
                     strips.push(Triangles{vertices : strip, mode : TrianglesMode::Fan});
                     //log("Finished with FAN");
                     break 'outer;
                 }
 
-
-                if !is_visible(&a, &b, &c, &polygon.orientation, &addresses, &polygon.points, vertices_left) {break};
+                if !is_ear(&a, &b, &c, orientation, &addresses, points) {break};
                 //log(format!("Visible {}, {}, {}", a.index, b.index, c.index).as_str());
             }
             //log(format!("FAN from {} to {}", a.index, b.index).as_str());
             strips.push(Triangles{vertices : strip, mode : TrianglesMode::Fan});
-            addresses[a.index] = b.index;
             count = 0;
         } else {
             count += 1;
@@ -228,78 +247,910 @@ pub fn tesselate_polygon(polygon : &Polygon) -> Vec<Triangles> {
         }
         a = b;
         b = c;
-        c = get_next(&polygon, &mut addresses, &mut index);
+        c = get_next(points, &mut addresses, &mut index);
     }
 
     strips
 
 }
 
+/**
+ * Merges holes into the outer contour by connecting each of them with a pair of coincident edges (a bridge),
+ * giving a single ring that encloses the same area. Holes are processed from the rightmost one,
+ * so that a bridge never crosses a hole which is not yet merged.
+ */
+fn bridge_holes(outer : &Vec<P>, holes : &Vec<Vec<P>>, orientation : &Orientation) -> Vec<P> {
+    let mut ring = outer.clone();
+
+    let mut holes : Vec<Vec<P>> = holes.iter()
+        .filter(|hole| hole.len() >= 3)
+        .map(|hole| {
+            // holes have to be traversed in the opposite direction than the outer contour
+            let mut hole = hole.clone();
+            if (contour_orientation(&hole) == *orientation) {
+                hole.reverse();
+            }
+            hole
+        })
+        .collect();
+
+    holes.sort_by(|a, b| rightmost_point(b).1.x.total_cmp(&rightmost_point(a).1.x));
+
+    for hole in holes.iter() {
+        bridge_hole(&mut ring, hole, orientation);
+    }
+
+    ring
+}
+
+fn rightmost_point(points : &Vec<P>) -> (usize, P) {
+    let mut index = 0;
+    for i in 1..points.len() {
+        if (points[i].x > points[index].x) {
+            index = i;
+        }
+    }
+    (index, points[index])
+}
 
+/**
+ * Connects the hole with a vertex of the ring visible from the hole's rightmost vertex
+ */
+fn bridge_hole(ring : &mut Vec<P>, hole : &Vec<P>, orientation : &Orientation) {
+    let (m_index, m) = rightmost_point(hole);
 
-pub fn normalize_polygon(polygon : &mut Polygon) {
+    // cast a ray from m to the right and find the closest edge it hits
+    let mut closest_x = f32::INFINITY;
+    let mut closest_edge = None;
+    for i in 0..ring.len() {
+        let p1 = ring[i];
+        let p2 = ring[(i + 1) % ring.len()];
 
+        if (p1.y == p2.y || (p1.y > m.y && p2.y > m.y) || (p1.y < m.y && p2.y < m.y)) {
+            continue;
+        }
 
+        let x = p1.x + (m.y - p1.y) * (p2.x - p1.x) / (p2.y - p1.y);
+        if (x >= m.x && x < closest_x) {
+            closest_x = x;
+            closest_edge = Some(i);
+        }
+    }
 
-    let mut start = 1;
+    let edge = match closest_edge {
+        Some(edge) => edge,
+        None => {
+            log("Hole lies outside of the polygon, skipping");
+            return;
+        }
+    };
 
-    loop {
-        let line_start_point = polygon.points[start - 1];
-        let mut index = start + 1;
-        let mut second_line_start = polygon.points[index];
-        loop {
-            index += 1;
-            if (index == polygon.points.len()) {
-                //log(format!("Break in {}", index).as_str());
+    let intersection = P::new(closest_x, m.y);
+    let edge_end = (edge + 1) % ring.len();
+    let mut candidate = if (ring[edge].x > ring[edge_end].x) {edge} else {edge_end};
+
+    if (!is_same(&intersection, &ring[candidate])) {
+        // vertices inside triangle (m, intersection, candidate) may obscure the candidate,
+        // the one with the smallest angle to the ray is visible
+        let p = ring[candidate];
+        let mut best_angle = f32::INFINITY;
+        let mut best_distance = f32::INFINITY;
+        for i in 0..ring.len() {
+            let v = ring[i];
+            if (is_same(&v, &p) || v.x < m.x || !is_in_triangle(&m, &intersection, &p, &v)) {
+                continue;
+            }
+            let angle = f32::atan2(f32::abs(v.y - m.y), v.x - m.x);
+            let distance = (v.x - m.x) * (v.x - m.x) + (v.y - m.y) * (v.y - m.y);
+            if (angle < best_angle || (angle == best_angle && distance < best_distance)) {
+                best_angle = angle;
+                best_distance = distance;
+                candidate = i;
+            }
+        }
+    }
+
+    // vertices of earlier bridges are duplicated, pick the copy whose corner opens towards m
+    let target = ring[candidate];
+    for i in 0..ring.len() {
+        if (is_same(&ring[i], &target) && is_in_sector(ring, i, &m, orientation)) {
+            candidate = i;
+            break;
+        }
+    }
+
+    let mut bridge : Vec<P> = Vec::with_capacity(hole.len() + 2);
+    for i in 0..=hole.len() {
+        bridge.push(hole[(m_index + i) % hole.len()]);
+    }
+    bridge.push(ring[candidate]);
+
+    ring.splice(candidate + 1..candidate + 1, bridge);
+}
+
+/**
+ * Checks whether point p lies within the inner angle of the ring at the vertex with a given index
+ */
+fn is_in_sector(ring : &Vec<P>, index : usize, p : &P, orientation : &Orientation) -> bool {
+    let prev = ring[(index + ring.len() - 1) % ring.len()];
+    let current = ring[index];
+    let next = ring[(index + 1) % ring.len()];
+
+    let after_prev = get_orientation(&prev, &current, p) == *orientation;
+    let before_next = get_orientation(&current, &next, p) == *orientation;
+
+    if (is_convex(&prev, &current, &next, orientation)) {
+        after_prev && before_next
+    } else {
+        after_prev || before_next
+    }
+}
+
+
+
+/**
+ * Outer contour of the polygon followed by its holes, wound opposite to the outer contour
+ */
+pub fn oriented_contours(polygon : &Polygon) -> Vec<Vec<P>> {
+
+    // self-intersecting contours can have no total area, declared orientation is used for them
+    let orientation = match contour_orientation(&polygon.points) {
+        Orientation::Colinear => polygon.orientation,
+        orientation => orientation
+    };
+
+    let mut contours : Vec<Vec<P>> = Vec::with_capacity(polygon.holes.len() + 1);
+    contours.push(polygon.points.clone());
+    for hole in polygon.holes.iter() {
+        // holes are always cut out, so they have to wind in the opposite direction than the outer contour
+        let mut hole = hole.clone();
+        if (contour_orientation(&hole) == orientation) {
+            hole.reverse();
+        }
+        contours.push(hole);
+    }
+
+    contours
+}
+
+/**
+ * Resolves intersections and overlaps of any number of closed contours according to the fill rule.
+ * Returns simple polygons (possibly with holes) covering exactly the filled area,
+ * with clockwise outer contours and counter-clockwise holes.
+ */
+pub fn normalize_contours(contours : &Vec<Vec<P>>, fill_rule : FillRule) -> Vec<Polygon> {
+
+    // contours which neither cross nor touch only have to be oriented by how they nest
+    let loops = match nested_loops(contours, &fill_rule) {
+        Some(loops) => loops,
+        None => {
+            let arrangement = Arrangement::build(contours);
+            let boundary = arrangement.boundary_edges(&fill_rule);
+            trace_loops(&arrangement.vertices, boundary)
+        }
+    };
+
+    let mut outers : Vec<(f32, Polygon)> = Vec::new();
+    let mut holes : Vec<Vec<P>> = Vec::new();
+    for contour in loops {
+        let area = signed_area(&contour);
+        if (area > 0.0) {
+            outers.push((area, Polygon{orientation : Orientation::Clockwise, points : contour, holes : Vec::new(), fill_rule : fill_rule}));
+        } else if (area < 0.0) {
+            holes.push(contour);
+        }
+    }
+
+    // every hole belongs to the smallest outer contour containing it
+    for hole in holes {
+        let mut container : Option<usize> = None;
+        for (i, (area, outer)) in outers.iter().enumerate() {
+            if (contains_point(&outer.points, &hole[0]) && container.map_or(true, |c| *area < outers[c].0)) {
+                container = Some(i);
+            }
+        }
+        match container {
+            Some(i) => outers[i].1.holes.push(hole),
+            None => log("Hole without outer contour, skipping"),
+        }
+    }
+
+    outers.into_iter().map(|(_, polygon)| polygon).collect()
+}
+
+/**
+ * Fills the path according to the fill rule, every subpath is treated as closed
+ */
+pub fn tesselate_path(path : &Path, fill_rule : FillRule) -> Vec<Triangles> {
+    let contours : Vec<Vec<P>> = path.flatten().iter()
+        .map(|subpath| subpath.points.iter().map(P::from_point).collect())
+        .collect();
+
+    normalize_contours(&contours, fill_rule).iter().flat_map(tesselate_polygon).collect()
+}
+
+/**
+ * Fills the path like `tesselate_path`, giving a single indexed triangle list
+ */
+pub fn tesselate_path_indexed(path : &Path, fill_rule : FillRule) -> IndexedTriangles {
+    index_triangles(&tesselate_path(path, fill_rule))
+}
+
+/**
+ * Turns strips and fans into one triangle list, vertices at the same position are shared
+ * and triangles with a repeated vertex are left out
+ */
+pub fn index_triangles<'a>(parts : impl IntoIterator<Item = &'a Triangles>) -> IndexedTriangles {
+    let mut vertices = Vec::new();
+    let mut indices : Vec<u32> = Vec::new();
+    let mut positions : HashMap<(u32, u32), u32> = HashMap::new();
+
+    for triangles in parts {
+        let part : Vec<u32> = triangles.vertices.chunks_exact(2).map(|v| {
+            *positions.entry((v[0].to_bits(), v[1].to_bits())).or_insert_with(|| {
+                vertices.extend_from_slice(v);
+                (vertices.len() / 2 - 1) as u32
+            })
+        }).collect();
+
+        for i in 2..part.len() {
+            let triangle = match triangles.mode {
+                TrianglesMode::Strip => [part[i - 2], part[i - 1], part[i]],
+                TrianglesMode::Fan => [part[0], part[i - 1], part[i]],
+            };
+            if (triangle[0] != triangle[1] && triangle[1] != triangle[2] && triangle[0] != triangle[2]) {
+                indices.extend_from_slice(&triangle);
+            }
+        }
+    }
+
+    IndexedTriangles{indices : compact_indices(indices, vertices.len() / 2), vertices}
+}
+
+/**
+ * Joins triangle lists one after another, indices are shifted past the vertices of the previous lists
+ */
+pub fn concat_triangles<'a>(meshes : impl IntoIterator<Item = &'a IndexedTriangles>) -> IndexedTriangles {
+    let mut vertices = Vec::new();
+    let mut indices : Vec<u32> = Vec::new();
+    for mesh in meshes {
+        let offset = (vertices.len() / 2) as u32;
+        match &mesh.indices {
+            Indices::U16(part) => indices.extend(part.iter().map(|i| *i as u32 + offset)),
+            Indices::U32(part) => indices.extend(part.iter().map(|i| *i + offset)),
+        }
+        vertices.extend_from_slice(&mesh.vertices);
+    }
+    IndexedTriangles{indices : compact_indices(indices, vertices.len() / 2), vertices}
+}
 
-                let intersection_point = get_line_intersection_point(line_start_point, polygon.points[start], second_line_start, polygon.points[0]);
+/**
+ * Indices stored in 16 bits when there are few enough vertices
+ */
+fn compact_indices(indices : Vec<u32>, vertices_count : usize) -> Indices {
+    if (vertices_count <= u16::MAX as usize + 1) {
+        Indices::U16(indices.into_iter().map(|i| i as u16).collect())
+    } else {
+        Indices::U32(indices)
+    }
+}
+
+/**
+ * Checks whether point lies inside a contour (even-odd crossing test)
+ */
+fn contains_point(points : &Vec<P>, p : &P) -> bool {
+    let mut inside = false;
+    for i in 0..points.len() {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        if ((a.y <= p.y) != (b.y <= p.y)) {
+            let x = a.x + (p.y - a.y) * (b.x - a.x) / (b.y - a.y);
+            if (x > p.x) {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+/**
+ * Boundaries of the filled area when no two contours cross or touch, None otherwise.
+ * Each contour then separates two regions whose windings differ by its own,
+ * so it's a boundary when the fill rule fills one of them only.
+ */
+fn nested_loops(contours : &Vec<Vec<P>>, fill_rule : &FillRule) -> Option<Vec<Vec<P>>> {
+
+    // repeated points are left out, lone points and empty contours add no segments
+    let contours : Vec<Vec<P>> = contours.iter().map(|contour| {
+        let mut points : Vec<P> = Vec::with_capacity(contour.len());
+        for p in contour.iter() {
+            if (points.last().map_or(true, |last| !is_same(last, p))) {
+                points.push(*p);
+            }
+        }
+        while (points.len() > 1 && is_same(&points[0], &points[points.len() - 1])) {
+            points.pop();
+        }
+        points
+    }).filter(|points| points.len() > 1).collect();
 
-                match intersection_point {
-                    Some(p) => intersection_found(start, index, p, polygon),
-                    None => ()// //log(format!("Not {}, {}", start, index).as_str())
+    // segments with their contour and position in it
+    let mut segments : Vec<(P, P)> = Vec::new();
+    let mut owners : Vec<(usize, usize)> = Vec::new();
+    for (k, contour) in contours.iter().enumerate() {
+        for i in 0..contour.len() {
+            segments.push((contour[i], contour[(i + 1) % contour.len()]));
+            owners.push((k, i));
+        }
+    }
+
+    for (i, j) in overlapping_pairs(&segments) {
+        let (a, b) = segments[i];
+        let (c, d) = segments[j];
+        let (k, m) = owners[i];
+        let (l, n) = owners[j];
+        let count = contours[k].len();
+
+        if (k == l && ((m + 1) % count == n || (n + 1) % count == m)) {
+            // neighbours share a vertex, they only meet elsewhere when one turns back along the other
+            let r = ((b.x - a.x) as f64, (b.y - a.y) as f64);
+            let s = ((d.x - c.x) as f64, (d.y - c.y) as f64);
+            let scale = (r.0 * r.0 + r.1 * r.1).sqrt() * (s.0 * s.0 + s.1 * s.1).sqrt();
+            if ((r.0 * s.1 - r.1 * s.0).abs() <= 1e-9 * scale && r.0 * s.0 + r.1 * s.1 < 0.0) {
+                return None;
+            }
+        } else if (!segment_intersections(&a, &b, &c, &d).is_empty()) {
+            return None;
+        }
+    }
+
+    let areas : Vec<f32> = contours.iter().map(signed_area).collect();
+    if (areas.iter().any(|area| *area == 0.0)) {
+        return None;
+    }
+    let bounds : Vec<(P, P)> = contours.iter().map(|contour| {
+        contour.iter().fold((contour[0], contour[0]), |(min, max), p| {
+            (P::new(min.x.min(p.x), min.y.min(p.y)), P::new(max.x.max(p.x), max.y.max(p.y)))
+        })
+    }).collect();
+
+    let mut loops = Vec::new();
+    for (k, contour) in contours.iter().enumerate() {
+        // winding and crossings of the region right outside the contour come from the contours around it
+        let p = contour[0];
+        let mut winding = 0;
+        let mut crossings = 0;
+        for (j, other) in contours.iter().enumerate() {
+            let (min, max) = bounds[j];
+            if (j != k && p.x >= min.x && p.x <= max.x && p.y >= min.y && p.y <= max.y && contains_point(other, &p)) {
+                winding += if (areas[j] > 0.0) {1} else {-1};
+                crossings += 1;
+            }
+        }
+
+        let filled_outside = fill_rule.is_inside(winding, crossings);
+        let filled_inside = fill_rule.is_inside(winding + if (areas[k] > 0.0) {1} else {-1}, crossings + 1);
+        if (filled_outside == filled_inside) {
+            continue;
+        }
+
+        // filled insides make outer contours with positive area, filled outsides make holes
+        let mut contour = contour.clone();
+        if ((areas[k] > 0.0) != filled_inside) {
+            contour.reverse();
+        }
+        loops.push(contour);
+    }
+
+    Some(loops)
+}
+
+/**
+ * Pairs of segments whose bounding boxes overlap, found by sweeping over the segments ordered by their left ends.
+ * Boxes are grown by the snapping distance, so that segments meeting within it are paired too.
+ */
+fn overlapping_pairs(segments : &Vec<(P, P)>) -> Vec<(usize, usize)> {
+    let margin = (1.0 / SNAP_PRECISION) as f32;
+    let boxes : Vec<(P, P)> = segments.iter().map(|(a, b)| {
+        (P::new(a.x.min(b.x) - margin, a.y.min(b.y) - margin), P::new(a.x.max(b.x) + margin, a.y.max(b.y) + margin))
+    }).collect();
+
+    let mut order : Vec<usize> = (0..segments.len()).collect();
+    order.sort_by(|i, j| boxes[*i].0.x.total_cmp(&boxes[*j].0.x));
+
+    let mut pairs = Vec::new();
+    for (position, i) in order.iter().enumerate() {
+        let (min, max) = boxes[*i];
+        for j in order[position + 1..].iter() {
+            let (other_min, other_max) = boxes[*j];
+            if (other_min.x > max.x) {
+                break;
+            }
+            if (other_min.y <= max.y && other_max.y >= min.y) {
+                pairs.push((usize::min(*i, *j), usize::max(*i, *j)));
+            }
+        }
+    }
+    pairs
+}
+
+/**
+ * Points closer than 1 / SNAP_PRECISION are treated as one vertex of the arrangement
+ */
+const SNAP_PRECISION : f64 = 1024.0;
+
+/**
+ * Most times the arrangement is split again after snapping
+ */
+const MAX_SPLIT_ROUNDS : usize = 8;
+
+/**
+ * Edge between two vertices of the arrangement, merged from all input segments covering it
+ */
+struct ArrangementEdge {
+    from : usize,
+    to : usize,
+    /** sum of directions of covering segments, +1 for each going from -> to, -1 for each going to -> from */
+    winding : i32,
+    /** number of covering segments */
+    count : u32,
+}
+
+/**
+ * Planar arrangement of contours, where segments are split at all their intersections
+ * so that edges meet only at their end vertices
+ */
+struct Arrangement {
+    vertices : Vec<P>,
+    edges : Vec<ArrangementEdge>,
+}
+
+impl Arrangement {
+
+    fn build(contours : &Vec<Vec<P>>) -> Arrangement {
+
+        let mut segments : Vec<(P, P, i32, u32)> = Vec::new();
+        for contour in contours.iter() {
+            for i in 0..contour.len() {
+                let a = contour[i];
+                let b = contour[(i + 1) % contour.len()];
+                if (!is_same(&a, &b)) {
+                    segments.push((a, b, 1, 1));
                 }
+            }
+        }
+
+        // snapping moves vertices a little, which can make edges cross again, so splitting is repeated until it changes nothing
+        let mut arrangement = Arrangement::split(&segments);
+        for _ in 0..MAX_SPLIT_ROUNDS {
+            let segments : Vec<(P, P, i32, u32)> = arrangement.edges.iter()
+                .map(|edge| (arrangement.vertices[edge.from], arrangement.vertices[edge.to], edge.winding, edge.count))
+                .collect();
+            let next = Arrangement::split(&segments);
+            let done = next.edges.len() == arrangement.edges.len();
+            arrangement = next;
+            if (done) {
                 break;
             }
-            let second_line_end = polygon.points[index];
-            let intersection_point = get_line_intersection_point(line_start_point, polygon.points[start], second_line_start, second_line_end);
+        }
+        arrangement
+    }
+
+    /**
+     * Splits segments at all their intersections and merges the parts into edges. Every segment comes
+     * with the winding and the count of segments it stands for.
+     */
+    fn split(segments : &Vec<(P, P, i32, u32)>) -> Arrangement {
 
-            match intersection_point {
-                Some(p) => intersection_found(start, index, p, polygon),
-                None => ()//alog(format!("Not {}, {}", start, index).as_str())
+        // positions along each segment at which it has to be split
+        let mut splits : Vec<Vec<(f64, P)>> = segments.iter().map(|(a, b, _, _)| vec![(0.0, *a), (1.0, *b)]).collect();
+
+        let ends : Vec<(P, P)> = segments.iter().map(|(a, b, _, _)| (*a, *b)).collect();
+        for (i, j) in overlapping_pairs(&ends) {
+            let (a, b) = ends[i];
+            let (c, d) = ends[j];
+            for (t, s, p) in segment_intersections(&a, &b, &c, &d) {
+                splits[i].push((t, p));
+                splits[j].push((s, p));
             }
+        }
+
+        let mut vertices : Vec<P> = Vec::new();
+        let mut vertex_ids : HashMap<(i64, i64), usize> = HashMap::new();
+        let mut edge_ids : HashMap<(usize, usize), usize> = HashMap::new();
+        let mut edges : Vec<ArrangementEdge> = Vec::new();
+
+        for (split, (_, _, winding, count)) in splits.iter_mut().zip(segments.iter()) {
+            split.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut previous : Option<usize> = None;
+            for (_, p) in split.iter() {
+                let key = ((p.x as f64 * SNAP_PRECISION).round() as i64, (p.y as f64 * SNAP_PRECISION).round() as i64);
+                let vertex = *vertex_ids.entry(key).or_insert_with(|| {
+                    vertices.push(P::new((key.0 as f64 / SNAP_PRECISION) as f32, (key.1 as f64 / SNAP_PRECISION) as f32));
+                    vertices.len() - 1
+                });
 
-            second_line_start = second_line_end;
+                if let Some(from) = previous {
+                    if (from != vertex) {
+                        let key = (usize::min(from, vertex), usize::max(from, vertex));
+                        let direction = if (from == key.0) {1} else {-1};
+                        let id = *edge_ids.entry(key).or_insert_with(|| {
+                            edges.push(ArrangementEdge{from : key.0, to : key.1, winding : 0, count : 0});
+                            edges.len() - 1
+                        });
+                        edges[id].winding += direction * winding;
+                        edges[id].count += count;
+                    }
+                }
+                previous = Some(vertex);
+            }
         }
 
-        start += 1;
-        if (start + 1 == polygon.points.len()) {
-            // //log(format!("Break out {}", start).as_str());
-            break;
+        Arrangement{vertices, edges}
+    }
+
+    /**
+     * Finds edges separating filled and empty regions, directed so that the filled region is on their left
+     * (in y-up coordinates), which makes outer contours clockwise in screen coordinates
+     */
+    fn boundary_edges(&self, fill_rule : &FillRule) -> Vec<(usize, usize)> {
+        let mut boundary = Vec::new();
+
+        let by_x = EdgeBuckets::new(self.edges.iter().map(|edge| (self.vertices[edge.from].x as f64, self.vertices[edge.to].x as f64)));
+        let by_y = EdgeBuckets::new(self.edges.iter().map(|edge| (self.vertices[edge.from].y as f64, self.vertices[edge.to].y as f64)));
+
+        for (i, edge) in self.edges.iter().enumerate() {
+            let a = self.vertices[edge.from];
+            let b = self.vertices[edge.to];
+            // rays cross edges steeply, so that they pass few other edges close to them
+            let horizontal = (b.x - a.x).abs() > (b.y - a.y).abs();
+
+            // winding of the region right next to the edge, on its +x side (or +y side for horizontal edges)
+            let (winding, crossings) = self.winding_at(i, horizontal, if (horizontal) {&by_x} else {&by_y});
+
+            // crossing the edge itself leads to the other side
+            let edge_sign = if (horizontal) {
+                if (b.x < a.x) {1} else {-1}
+            } else {
+                if (b.y > a.y) {1} else {-1}
+            };
+            let other_winding = winding + edge_sign * edge.winding;
+            let other_crossings = crossings + edge.count;
+
+            let filled = fill_rule.is_inside(winding, crossings);
+            let other_filled = fill_rule.is_inside(other_winding, other_crossings);
+            if (filled == other_filled) {
+                continue;
+            }
+
+            // filled side has to be on the left of the directed edge
+            let forward = if (horizontal) {
+                (b.x > a.x) == filled
+            } else {
+                (b.y < a.y) == filled
+            };
+            if (forward) {
+                boundary.push((edge.from, edge.to));
+            } else {
+                boundary.push((edge.to, edge.from));
+            }
         }
-        // //log("continue");
+
+        boundary
+    }
+
+    /**
+     * Casts a ray from the middle of the edge towards +x (or +y for horizontal edges)
+     * and sums windings and crossings of the other edges it passes, which are looked up in the buckets
+     */
+    fn winding_at(&self, edge_index : usize, horizontal : bool, buckets : &EdgeBuckets) -> (i32, u32) {
+        let edge = &self.edges[edge_index];
+        let a = self.vertices[edge.from];
+        let b = self.vertices[edge.to];
+        let mx = (a.x as f64 + b.x as f64) / 2.0;
+        let my = (a.y as f64 + b.y as f64) / 2.0;
+
+        let mut winding = 0;
+        let mut crossings = 0;
+        for i in buckets.get(if (horizontal) {mx} else {my}) {
+            if (*i == edge_index) {
+                continue;
+            }
+            let other = &self.edges[*i];
+            let c = self.vertices[other.from];
+            let d = self.vertices[other.to];
+            let (cx, cy, dx, dy) = (c.x as f64, c.y as f64, d.x as f64, d.y as f64);
+
+            if (horizontal) {
+                if ((cx <= mx) != (dx <= mx)) {
+                    let y = cy + (mx - cx) * (dy - cy) / (dx - cx);
+                    if (y > my) {
+                        winding += if (dx < cx) {other.winding} else {-other.winding};
+                        crossings += other.count;
+                    }
+                }
+            } else {
+                if ((cy <= my) != (dy <= my)) {
+                    let x = cx + (my - cy) * (dx - cx) / (dy - cy);
+                    if (x > mx) {
+                        winding += if (dy > cy) {other.winding} else {-other.winding};
+                        crossings += other.count;
+                    }
+                }
+            }
+        }
+
+        (winding, crossings)
     }
 }
 
-fn intersection_found(first: usize, second: usize, point : P, polygon: &mut Polygon) {
-    
-    //log(format!("Intersection found between segments {} and {} at point {}, {}", first, second, point.x, point.y).as_str());
-    let mut a = first;
-    let mut b = second - 1;
-    loop {
-        let var = polygon.points[b];
-        polygon.points[b] = polygon.points[a];
-        polygon.points[a] = var;
-
-        a += 1;
-        b -= 1;
-
-        if (a >= b) {
-            break;
+/**
+ * Edges sorted into equally wide buckets along one axis by the range of coordinates they span
+ */
+struct EdgeBuckets {
+    min : f64,
+    size : f64,
+    buckets : Vec<Vec<usize>>,
+}
+
+impl EdgeBuckets {
+
+    fn new(ranges : impl ExactSizeIterator<Item = (f64, f64)>) -> EdgeBuckets {
+        let ranges : Vec<(f64, f64)> = ranges.map(|(a, b)| (a.min(b), a.max(b))).collect();
+        let min = ranges.iter().map(|range| range.0).fold(f64::INFINITY, f64::min);
+        let max = ranges.iter().map(|range| range.1).fold(f64::NEG_INFINITY, f64::max);
+        let count = (ranges.len() as f64).sqrt().ceil().max(1.0) as usize;
+        let size = if (max > min) {(max - min) / count as f64} else {1.0};
+
+        let mut buckets = EdgeBuckets{min, size, buckets : vec![Vec::new(); count]};
+        for (i, (low, high)) in ranges.iter().enumerate() {
+            for bucket in buckets.index(*low)..=buckets.index(*high) {
+                buckets.buckets[bucket].push(i);
+            }
+        }
+        buckets
+    }
+
+    fn index(&self, value : f64) -> usize {
+        (((value - self.min) / self.size).floor().max(0.0) as usize).min(self.buckets.len() - 1)
+    }
+
+    /**
+     * Edges whose range may contain the value
+     */
+    fn get(&self, value : f64) -> &Vec<usize> {
+        &self.buckets[self.index(value)]
+    }
+}
+
+/**
+ * Finds all points shared by segments ab and cd, returned together with their positions along both segments
+ */
+fn segment_intersections(a : &P, b : &P, c : &P, d : &P) -> Vec<(f64, f64, P)> {
+    const EPSILON : f64 = 1e-9;
+
+    let (ax, ay, bx, by) = (a.x as f64, a.y as f64, b.x as f64, b.y as f64);
+    let (cx, cy, dx, dy) = (c.x as f64, c.y as f64, d.x as f64, d.y as f64);
+
+    let r = (bx - ax, by - ay);
+    let s = (dx - cx, dy - cy);
+    let q = (cx - ax, cy - ay);
+
+    let denominator = r.0 * s.1 - r.1 * s.0;
+    let scale = (r.0 * r.0 + r.1 * r.1).sqrt() * (s.0 * s.0 + s.1 * s.1).sqrt();
+
+    let mut result = Vec::new();
+
+    if (denominator.abs() > EPSILON * scale) {
+        let t = (q.0 * s.1 - q.1 * s.0) / denominator;
+        let u = (q.0 * r.1 - q.1 * r.0) / denominator;
+        if (t >= -EPSILON && t <= 1.0 + EPSILON && u >= -EPSILON && u <= 1.0 + EPSILON) {
+            let t = t.clamp(0.0, 1.0);
+            let u = u.clamp(0.0, 1.0);
+            result.push((t, u, P::new((ax + t * r.0) as f32, (ay + t * r.1) as f32)));
+        }
+    } else if ((q.0 * r.1 - q.1 * r.0).abs() <= EPSILON * scale.max(1.0)) {
+        // colinear segments, overlapping parts are split at the ends of the other segment
+        let r_length = r.0 * r.0 + r.1 * r.1;
+        let s_length = s.0 * s.0 + s.1 * s.1;
+        for (p, px, py) in [(c, cx, cy), (d, dx, dy)] {
+            let t = ((px - ax) * r.0 + (py - ay) * r.1) / r_length;
+            if (t > 0.0 && t < 1.0) {
+                let u = if (p.x == c.x && p.y == c.y) {0.0} else {1.0};
+                result.push((t, u, *p));
+            }
+        }
+        for (p, px, py) in [(a, ax, ay), (b, bx, by)] {
+            let u = ((px - cx) * s.0 + (py - cy) * s.1) / s_length;
+            if (u > 0.0 && u < 1.0) {
+                let t = if (p.x == a.x && p.y == a.y) {0.0} else {1.0};
+                result.push((t, u, *p));
+            }
         }
-        
     }
 
-    polygon.points.insert(second, point);
-    polygon.points.insert(first, point);
+    result
+}
 
-}
\ No newline at end of file
+/**
+ * Joins directed boundary edges into closed loops. At vertices shared by more loops
+ * the sharpest left turn is taken, so that loops touching at a single point are kept apart.
+ */
+fn trace_loops(vertices : &Vec<P>, edges : Vec<(usize, usize)>) -> Vec<Vec<P>> {
+    let mut outgoing : HashMap<usize, Vec<usize>> = HashMap::new();
+    for (i, (from, _)) in edges.iter().enumerate() {
+        outgoing.entry(*from).or_default().push(i);
+    }
+
+    let mut used = vec![false; edges.len()];
+    let mut loops = Vec::new();
+
+    for start in 0..edges.len() {
+        if (used[start]) {
+            continue;
+        }
+
+        let mut contour = Vec::new();
+        let mut current = start;
+        loop {
+            used[current] = true;
+            let (from, to) = edges[current];
+            contour.push(vertices[from]);
+
+            let a = vertices[from];
+            let b = vertices[to];
+            let incoming = ((b.x - a.x) as f64, (b.y - a.y) as f64);
+
+            let mut next : Option<usize> = None;
+            let mut best_turn = f64::NEG_INFINITY;
+            for candidate in outgoing.get(&to).into_iter().flatten() {
+                if (used[*candidate] && *candidate != start) {
+                    continue;
+                }
+                let c = vertices[edges[*candidate].1];
+                let outgoing = ((c.x - b.x) as f64, (c.y - b.y) as f64);
+                let turn = f64::atan2(incoming.0 * outgoing.1 - incoming.1 * outgoing.0, incoming.0 * outgoing.0 + incoming.1 * outgoing.1);
+                if (turn > best_turn) {
+                    best_turn = turn;
+                    next = Some(*candidate);
+                }
+            }
+
+            match next {
+                Some(n) if n != start => current = n,
+                _ => break,
+            }
+        }
+
+        if (contour.len() >= 3) {
+            loops.push(contour);
+        }
+    }
+
+    loops
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+
+    fn path(contours : &[Vec<P>]) -> Path {
+        let mut path = Path::new();
+        for contour in contours {
+            path.move_to(contour[0].x as f64, contour[0].y as f64);
+            for p in &contour[1..] {
+                path.line_to(p.x as f64, p.y as f64);
+            }
+            path.close();
+        }
+        path
+    }
+
+    fn rect(x : f32, y : f32, width : f32, height : f32) -> Vec<P> {
+        vec![P::new(x, y), P::new(x + width, y), P::new(x + width, y + height), P::new(x, y + height)]
+    }
+
+    /** total area of the triangles, which don't overlap when the fill is right */
+    fn triangles_area(triangles : &[Triangles]) -> f32 {
+        let indexed = index_triangles(triangles);
+        let vertex = |i : usize| P::new(indexed.vertices[indexed.indices.get(i) * 2], indexed.vertices[indexed.indices.get(i) * 2 + 1]);
+        (0..indexed.indices.len() / 3).map(|t| {
+            let (a, b, c) = (vertex(t * 3), vertex(t * 3 + 1), vertex(t * 3 + 2));
+            ((b.x - a.x) * (c.y - a.y) - (c.x - a.x) * (b.y - a.y)).abs() / 2.0
+        }).sum()
+    }
+
+    fn assert_area(actual : f32, expected : f32) {
+        assert!((actual - expected).abs() < 1e-3 * expected.max(1.0), "area {} instead of {}", actual, expected);
+    }
+
+    /** five-pointed star drawn in one stroke, its centre winds twice */
+    fn star(outer_radius : f32) -> Vec<P> {
+        (0..5).map(|i| {
+            let angle = -PI / 2.0 + i as f32 * 4.0 * PI / 5.0;
+            P::new(outer_radius * angle.cos(), outer_radius * angle.sin())
+        }).collect()
+    }
+
+    /** regular polygon with the radius and the given count of corners, starting from the angle */
+    fn regular(count : usize, radius : f32, start : f32) -> Vec<P> {
+        (0..count).map(|i| {
+            let angle = start + i as f32 * 2.0 * PI / count as f32;
+            P::new(radius * angle.cos(), radius * angle.sin())
+        }).collect()
+    }
+
+    #[test]
+    fn polygon_with_hole() {
+        let mut hole = rect(3.0, 3.0, 4.0, 4.0);
+        hole.reverse();
+        let contours = vec![rect(0.0, 0.0, 10.0, 10.0), hole];
+        for fill_rule in [FillRule::NonZero, FillRule::EvenOdd] {
+            let polygons = normalize_contours(&contours, fill_rule);
+            assert_eq!(polygons.len(), 1);
+            assert_eq!(polygons[0].holes.len(), 1);
+            assert_area(triangles_area(&tesselate_path(&path(&contours), fill_rule)), 84.0);
+        }
+
+        // a hole winding like the outer contour is cut out only by the even-odd rule
+        let contours = vec![rect(0.0, 0.0, 10.0, 10.0), rect(3.0, 3.0, 4.0, 4.0)];
+        assert_area(triangles_area(&tesselate_path(&path(&contours), FillRule::NonZero)), 100.0);
+        assert_area(triangles_area(&tesselate_path(&path(&contours), FillRule::EvenOdd)), 84.0);
+    }
+
+    #[test]
+    fn star_fill_rules() {
+        let radius = 10.0;
+        let inner_radius = radius * (2.0 * PI / 5.0).cos() / (PI / 5.0).cos();
+        // outline of the star as a ten-gon alternating between the tips and the inner corners
+        let outline : Vec<P> = (0..10).map(|i| {
+            let r = if (i % 2 == 0) {radius} else {inner_radius};
+            let angle = -PI / 2.0 + i as f32 * PI / 5.0;
+            P::new(r * angle.cos(), r * angle.sin())
+        }).collect();
+        let pentagon = regular(5, inner_radius, PI / 2.0);
+        let star_area = signed_area(&outline).abs();
+        let pentagon_area = signed_area(&pentagon).abs();
+
+        let contours = vec![star(radius)];
+        assert_area(triangles_area(&tesselate_path(&path(&contours), FillRule::NonZero)), star_area);
+        assert_area(triangles_area(&tesselate_path(&path(&contours), FillRule::EvenOdd)), star_area - pentagon_area);
+        assert_eq!(normalize_contours(&contours, FillRule::NonZero).len(), 1);
+        assert_eq!(normalize_contours(&contours, FillRule::EvenOdd).len(), 5);
+    }
+
+    #[test]
+    fn nested_contours() {
+        let area = |contour : &Vec<P>| signed_area(contour).abs();
+        let outer = regular(1000, 10.0, 0.0);
+        let middle = regular(1000, 6.0, 0.0);
+        let mut inner = regular(1000, 3.0, 0.0);
+        inner.reverse();
+        let contours = vec![outer.clone(), middle.clone(), inner.clone()];
+
+        // contours without intersections skip the arrangement, touching ones don't
+        assert!(nested_loops(&contours, &FillRule::NonZero).is_some());
+        assert!(nested_loops(&vec![rect(0.0, 0.0, 10.0, 10.0), rect(10.0, 0.0, 10.0, 10.0)], &FillRule::NonZero).is_none());
+
+        assert_eq!(normalize_contours(&contours, FillRule::NonZero).len(), 1);
+        assert_area(triangles_area(&tesselate_path(&path(&contours), FillRule::NonZero)), area(&outer));
+        let polygons = normalize_contours(&contours, FillRule::EvenOdd);
+        assert_eq!(polygons.len(), 2);
+        assert!(polygons.iter().all(|polygon| signed_area(&polygon.points) > 0.0));
+        assert_area(triangles_area(&tesselate_path(&path(&contours), FillRule::EvenOdd)), area(&outer) - area(&middle) + area(&inner));
+    }
+
+    #[test]
+    fn figure_eight() {
+        let contours = vec![vec![P::new(0.0, 0.0), P::new(10.0, 10.0), P::new(10.0, 0.0), P::new(0.0, 10.0)]];
+        for fill_rule in [FillRule::NonZero, FillRule::EvenOdd] {
+            let polygons = normalize_contours(&contours, fill_rule);
+            assert_eq!(polygons.len(), 2);
+            assert!(polygons.iter().all(|polygon| polygon.holes.is_empty() && signed_area(&polygon.points) > 0.0));
+            assert_area(triangles_area(&tesselate_path(&path(&contours), fill_rule)), 50.0);
+        }
+    }
+}
@@ -0,0 +1,423 @@
+use std::collections::{HashMap, HashSet};
+
+use web_sys::{WebGl2RenderingContext, WebGlBuffer, WebGlFramebuffer, WebGlProgram, WebGlRenderbuffer, WebGlTexture, WebGlVertexArrayObject};
+use crate::matrix::Matrix3x3;
+use super::{backend::Backend, ramp::{bake_ramp, RampKey, RAMP_SIZE}, shaders::{create_shader_program, ShaderInfo}, Brush, ColorSpace, Gradient, IndexedTriangles, Indices, Spread, Triangles, TrianglesMode};
+
+const COORDS_PER_VERTEX : i32 = 2;
+
+/**
+ * Backend drawing on a canvas with WebGL 2, brushes are computed by the fragment shader
+ */
+pub struct WebGlBackend {
+    gl : WebGl2RenderingContext,
+    program : WebGlProgram,
+    shader_info : ShaderInfo,
+    /** size of the canvas */
+    width : f32,
+    height : f32,
+    /** size of the area drawn into, in canvas units */
+    area : [f32; 2],
+    transform : Matrix3x3,
+    /** buffer refilled for every draw of vertices which are not kept in a mesh */
+    stream_buffer : WebGlBuffer,
+    stream_vao : WebGlVertexArrayObject,
+    /** textures with ramps of the gradients, kept while they're painted with */
+    ramps : HashMap<RampKey, WebGlTexture>,
+    /** ramps used since the last clear */
+    used_ramps : HashSet<RampKey>,
+}
+
+/**
+ * Triangle list kept in buffers between draws, read through a vertex array
+ */
+pub struct Mesh {
+    vertex_buffer : WebGlBuffer,
+    index_buffer : WebGlBuffer,
+    vao : WebGlVertexArrayObject,
+    /** number of coordinates in the vertex buffer */
+    vertices_length : usize,
+    /** number of indices in the index buffer */
+    indices_length : usize,
+    /** type of the indices, unsigned short or unsigned int */
+    index_type : u32,
+}
+
+/**
+ * Framebuffer drawn into instead of the canvas, size is in pixels
+ */
+pub struct OffscreenTarget {
+    framebuffer : WebGlFramebuffer,
+    renderbuffer : WebGlRenderbuffer,
+    width : u32,
+    height : u32,
+}
+
+impl WebGlBackend {
+    pub fn create(gl : WebGl2RenderingContext) -> WebGlBackend {
+
+        let (program, shader_info) = create_shader_program(&gl);
+        gl.use_program(Some(&program));
+
+        // colours are premultiplied by alpha, as the canvas expects them
+        gl.enable(WebGl2RenderingContext::BLEND);
+        gl.blend_func(WebGl2RenderingContext::ONE, WebGl2RenderingContext::ONE_MINUS_SRC_ALPHA);
+
+        let buffer = gl.create_buffer().ok_or("Failed to create buffer").unwrap();
+        let vao = create_vertex_array(&gl, shader_info.a_pos, &buffer);
+        let mut backend = WebGlBackend{
+            gl,
+            program : program,
+            shader_info : shader_info,
+            width : 0.0,
+            height : 0.0,
+            area : [0.0, 0.0],
+            transform : Matrix3x3::identity(),
+            stream_buffer : buffer,
+            stream_vao : vao,
+            ramps : HashMap::new(),
+            used_ramps : HashSet::new(),
+        };
+        backend.set_viewport(0.0, 0.0, 1.0);
+
+        backend
+    }
+
+    /**
+     * Replaces content of the buffer with the vertices, or only overwrites it when their number is the same
+     */
+    fn write_vertices(&self, buffer : &WebGlBuffer, vertices : &[f32], reuse_storage : bool, usage : u32) {
+        self.gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(buffer));
+
+        // Note that `Float32Array::view` is somewhat dangerous (hence the
+        // `unsafe`!). This is creating a raw view into our module's
+        // `WebAssembly.Memory` buffer, but if we allocate more pages for ourself
+        // (aka do a memory allocation in Rust) it'll cause the buffer to change,
+        // causing the `Float32Array` to be invalid.
+        //
+        // As a result, after `Float32Array::view` we have to be very careful not to
+        // do any memory allocations before it's dropped.
+        unsafe {
+            let positions_array_buf_view = js_sys::Float32Array::view(vertices);
+            self.write_buffer(WebGl2RenderingContext::ARRAY_BUFFER, &positions_array_buf_view, reuse_storage, usage);
+        }
+    }
+
+    /**
+     * Replaces content of the index buffer, the vertex array it belongs to has to be bound
+     */
+    fn write_indices(&self, buffer : &WebGlBuffer, indices : &Indices, reuse_storage : bool) {
+        self.gl.bind_buffer(WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER, Some(buffer));
+
+        // same as with the vertices, nothing may be allocated while the views exist
+        unsafe {
+            match indices {
+                Indices::U16(indices) => {
+                    let view = js_sys::Uint16Array::view(indices);
+                    self.write_buffer(WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER, &view, reuse_storage, WebGl2RenderingContext::STATIC_DRAW);
+                },
+                Indices::U32(indices) => {
+                    let view = js_sys::Uint32Array::view(indices);
+                    self.write_buffer(WebGl2RenderingContext::ELEMENT_ARRAY_BUFFER, &view, reuse_storage, WebGl2RenderingContext::STATIC_DRAW);
+                },
+            }
+        }
+    }
+
+    fn write_buffer(&self, target : u32, data : &js_sys::Object, reuse_storage : bool, usage : u32) {
+        if (reuse_storage) {
+            self.gl.buffer_sub_data_with_i32_and_array_buffer_view(target, 0, data);
+        } else {
+            self.gl.buffer_data_with_array_buffer_view(target, data, usage);
+        }
+    }
+
+    /**
+     * Uploads the triangles into the buffers of the mesh, keeping their storage if the sizes didn't change
+     */
+    fn write_mesh(&self, mesh : &mut Mesh, triangles : &IndexedTriangles, reuse_storage : bool) {
+        let index_type = match triangles.indices {
+            Indices::U16(_) => WebGl2RenderingContext::UNSIGNED_SHORT,
+            Indices::U32(_) => WebGl2RenderingContext::UNSIGNED_INT,
+        };
+        let same_vertices = reuse_storage && mesh.vertices_length == triangles.vertices.len();
+        let same_indices = reuse_storage && mesh.indices_length == triangles.indices.len() && mesh.index_type == index_type;
+
+        self.write_vertices(&mesh.vertex_buffer, &triangles.vertices, same_vertices, WebGl2RenderingContext::STATIC_DRAW);
+        self.gl.bind_vertex_array(Some(&mesh.vao));
+        self.write_indices(&mesh.index_buffer, &triangles.indices, same_indices);
+
+        mesh.vertices_length = triangles.vertices.len();
+        mesh.indices_length = triangles.indices.len();
+        mesh.index_type = index_type;
+    }
+
+    /**
+     * Draws vertices used only once, through the buffer kept for them
+     */
+    fn draw_stream(&self, mode : u32, vertices : &[f32]) {
+        self.write_vertices(&self.stream_buffer, vertices, false, WebGl2RenderingContext::STREAM_DRAW);
+        self.gl.bind_vertex_array(Some(&self.stream_vao));
+        self.gl.draw_arrays(mode, 0, vertices.len() as i32 / COORDS_PER_VERTEX);
+    }
+
+    pub fn resize_viewport(&mut self, width : f32, height : f32) {
+        self.width = width;
+        self.height = height;
+        self.set_viewport(width, height, 1.0);
+    }
+
+    /**
+     * Size of the drawn area in canvas units and the number of pixels per unit
+     */
+    fn set_viewport(&mut self, width : f32, height : f32, scale : f32) {
+        self.area = [width, height];
+        self.gl.uniform2f(self.shader_info.u_res.as_ref(), width, height);
+
+        self.gl.viewport(0, 0, (width * scale).round() as i32, (height * scale).round() as i32);
+    }
+
+    /**
+     * Redirects drawing into a framebuffer showing the area of the size in canvas units, scaled by the number of pixels per unit
+     */
+    pub fn begin_offscreen(&mut self, width : f32, height : f32, scale : f32) -> Result<OffscreenTarget, String> {
+        let pixel_width = (width * scale).round() as u32;
+        let pixel_height = (height * scale).round() as u32;
+
+        let framebuffer = self.gl.create_framebuffer().ok_or("Failed to create framebuffer")?;
+        let renderbuffer = self.gl.create_renderbuffer().ok_or("Failed to create renderbuffer")?;
+        self.gl.bind_renderbuffer(WebGl2RenderingContext::RENDERBUFFER, Some(&renderbuffer));
+        self.gl.renderbuffer_storage(WebGl2RenderingContext::RENDERBUFFER, WebGl2RenderingContext::RGBA8, pixel_width as i32, pixel_height as i32);
+        self.gl.bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, Some(&framebuffer));
+        self.gl.framebuffer_renderbuffer(WebGl2RenderingContext::FRAMEBUFFER, WebGl2RenderingContext::COLOR_ATTACHMENT0, WebGl2RenderingContext::RENDERBUFFER, Some(&renderbuffer));
+
+        let target = OffscreenTarget{framebuffer, renderbuffer, width : pixel_width, height : pixel_height};
+        if (self.gl.check_framebuffer_status(WebGl2RenderingContext::FRAMEBUFFER) != WebGl2RenderingContext::FRAMEBUFFER_COMPLETE) {
+            self.end_offscreen(target);
+            return Err(format!("Can't draw offscreen at {}x{} pixels", pixel_width, pixel_height));
+        }
+
+        self.set_viewport(width, height, scale);
+        Ok(target)
+    }
+
+    /**
+     * Reads pixels drawn into the target as premultiplied RGBA rows from the top, then goes back to drawing on the canvas
+     */
+    pub fn read_offscreen(&mut self, target : OffscreenTarget) -> Result<Vec<u8>, String> {
+        let row = target.width as usize * 4;
+        let mut pixels = vec![0; row * target.height as usize];
+        let result = self.gl.read_pixels_with_opt_u8_array(
+            0, 0, target.width as i32, target.height as i32,
+            WebGl2RenderingContext::RGBA, WebGl2RenderingContext::UNSIGNED_BYTE,
+            Some(&mut pixels)
+        );
+        self.end_offscreen(target);
+        result.map_err(|_| String::from("Failed to read pixels"))?;
+
+        // rows are read from the bottom
+        let flipped = pixels.chunks_exact(row).rev().flatten().copied().collect();
+        Ok(flipped)
+    }
+
+    fn end_offscreen(&mut self, target : OffscreenTarget) {
+        self.gl.bind_framebuffer(WebGl2RenderingContext::FRAMEBUFFER, None);
+        self.gl.bind_renderbuffer(WebGl2RenderingContext::RENDERBUFFER, None);
+        self.gl.delete_framebuffer(Some(&target.framebuffer));
+        self.gl.delete_renderbuffer(Some(&target.renderbuffer));
+        self.set_viewport(self.width, self.height, 1.0);
+    }
+
+    /**
+     * Sets uniforms of the gradient drawn as the brush type of the fragment shader
+     */
+    fn set_gradient(&mut self, brush_type : u32, gradient : &Gradient) {
+        let Some(inverse) = gradient.inverse_transform() else {
+            // gradient squashed into a line paints nothing
+            self.set_brush(&Brush::Color(0.0, 0.0, 0.0, 0.0));
+            return;
+        };
+        self.gl.uniform1ui(self.shader_info.u_brush_type.as_ref(), brush_type);
+        self.gl.uniform_matrix3fv_with_f32_array(self.shader_info.gradient_transform.as_ref(), false, &inverse.data());
+
+        self.gl.uniform2f(self.shader_info.gradient_start.as_ref(), gradient.x1, gradient.y1);
+        self.gl.uniform2f(self.shader_info.gradient_end.as_ref(), gradient.x2, gradient.y2);
+        self.gl.uniform2f(self.shader_info.gradient_radii.as_ref(), gradient.r1, gradient.r2);
+        let spread = match gradient.spread {
+            Spread::Pad => 0,
+            Spread::Repeat => 1,
+            Spread::Reflect => 2,
+        };
+        self.gl.uniform1ui(self.shader_info.gradient_spread.as_ref(), spread);
+        let ramp_space = match gradient.color_space {
+            ColorSpace::Srgb => 0,
+            ColorSpace::LinearSrgb => 1,
+            ColorSpace::Oklab | ColorSpace::Oklch(_) => 2,
+        };
+        self.gl.uniform1ui(self.shader_info.gradient_ramp_space.as_ref(), ramp_space);
+
+        let key = RampKey::new(gradient);
+        if (!self.ramps.contains_key(&key)) {
+            let texture = self.create_ramp_texture(gradient);
+            self.ramps.insert(key.clone(), texture);
+        }
+        self.gl.active_texture(WebGl2RenderingContext::TEXTURE0);
+        self.gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, self.ramps.get(&key));
+        self.gl.uniform1i(self.shader_info.gradient_ramp.as_ref(), 0);
+        self.used_ramps.insert(key);
+    }
+
+    /**
+     * Texture one texel high with the ramp of the gradient, read texel by texel without filtering
+     */
+    fn create_ramp_texture(&self, gradient : &Gradient) -> WebGlTexture {
+        let texture = self.gl.create_texture().ok_or("Failed to create texture").unwrap();
+        self.gl.bind_texture(WebGl2RenderingContext::TEXTURE_2D, Some(&texture));
+        for (parameter, value) in [
+            (WebGl2RenderingContext::TEXTURE_MIN_FILTER, WebGl2RenderingContext::NEAREST),
+            (WebGl2RenderingContext::TEXTURE_MAG_FILTER, WebGl2RenderingContext::NEAREST),
+            (WebGl2RenderingContext::TEXTURE_WRAP_S, WebGl2RenderingContext::CLAMP_TO_EDGE),
+            (WebGl2RenderingContext::TEXTURE_WRAP_T, WebGl2RenderingContext::CLAMP_TO_EDGE),
+        ] {
+            self.gl.tex_parameteri(WebGl2RenderingContext::TEXTURE_2D, parameter, value as i32);
+        }
+
+        let colors : Vec<f32> = bake_ramp(gradient).into_iter().flatten().collect();
+        // half floats keep more precision than bytes, and negative components of OKLab
+        self.gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+            WebGl2RenderingContext::TEXTURE_2D,
+            0,
+            WebGl2RenderingContext::RGBA16F as i32,
+            RAMP_SIZE as i32,
+            1,
+            0,
+            WebGl2RenderingContext::RGBA,
+            WebGl2RenderingContext::FLOAT,
+            Some(&js_sys::Float32Array::from(colors.as_slice())),
+        ).unwrap();
+        texture
+    }
+
+    /**
+     * Deletes textures of the ramps which were not used since the last time
+     */
+    fn release_unused_ramps(&mut self) {
+        let used = std::mem::take(&mut self.used_ramps);
+        let gl = &self.gl;
+        self.ramps.retain(|key, texture| {
+            let keep = used.contains(key);
+            if (!keep) {
+                gl.delete_texture(Some(texture));
+            }
+            keep
+        });
+    }
+}
+
+impl Backend for WebGlBackend {
+
+    type Mesh = Mesh;
+
+    fn create_mesh(&mut self, triangles : &IndexedTriangles) -> Mesh {
+        let vertex_buffer = self.gl.create_buffer().ok_or("Failed to create buffer").unwrap();
+        let index_buffer = self.gl.create_buffer().ok_or("Failed to create buffer").unwrap();
+        let vao = create_vertex_array(&self.gl, self.shader_info.a_pos, &vertex_buffer);
+        let mut mesh = Mesh{vertex_buffer, index_buffer, vao, vertices_length : 0, indices_length : 0, index_type : 0};
+        self.write_mesh(&mut mesh, triangles, false);
+        mesh
+    }
+
+    fn update_mesh(&mut self, mesh : &mut Mesh, triangles : &IndexedTriangles) {
+        self.write_mesh(mesh, triangles, true);
+    }
+
+    fn delete_mesh(&mut self, mesh : Mesh) {
+        self.gl.delete_vertex_array(Some(&mesh.vao));
+        self.gl.delete_buffer(Some(&mesh.vertex_buffer));
+        self.gl.delete_buffer(Some(&mesh.index_buffer));
+    }
+
+    fn draw_mesh(&mut self, mesh : &Mesh) {
+        self.gl.bind_vertex_array(Some(&mesh.vao));
+        self.gl.draw_elements_with_i32(WebGl2RenderingContext::TRIANGLES, mesh.indices_length as i32, mesh.index_type, 0);
+    }
+
+    fn clear(&mut self, brush : &Brush) {
+        // clearing starts a new frame, ramps of the previous one which are not painted with again can go
+        self.release_unused_ramps();
+
+        let [r, g, b, a] = match brush {
+            Brush::Color(r, g, b, a) => [*r, *g, *b, *a],
+            _ => [0.0, 0.0, 0.0, 0.0]
+        };
+        self.gl.clear_color(r * a, g * a, b * a, a);
+        self.gl.clear(WebGl2RenderingContext::COLOR_BUFFER_BIT);
+
+        if (!matches!(brush, Brush::Color(..))) {
+            let [width, height] = self.area;
+            let area = Triangles{vertices : vec![0.0, 0.0, width, 0.0, width, height, 0.0, height], mode : TrianglesMode::Fan};
+            let transform = self.transform;
+            self.set_transform(&Matrix3x3::identity());
+            self.set_brush(brush);
+            self.draw_triangles(&area);
+            self.set_transform(&transform);
+        }
+    }
+
+    fn set_transform(&mut self, matrix : &Matrix3x3) {
+        self.gl.uniform_matrix3fv_with_f32_array(self.shader_info.transform.as_ref(), false, &matrix.data());
+        self.transform = *matrix;
+    }
+
+    fn draw_triangles(&mut self, triangles : &Triangles) {
+        match triangles.mode {
+            TrianglesMode::Fan => self.draw_stream(WebGl2RenderingContext::TRIANGLE_FAN, &triangles.vertices),
+            TrianglesMode::Strip => self.draw_stream(WebGl2RenderingContext::TRIANGLE_STRIP, &triangles.vertices),
+        }
+    }
+
+    fn draw_lines(&mut self, points : &[f32]) {
+        self.draw_stream(WebGl2RenderingContext::LINES, points);
+    }
+
+    fn draw_points(&mut self, points : &[f32], size : f32) {
+        self.gl.uniform1f(self.shader_info.u_point_size.as_ref(), size);
+        self.draw_stream(WebGl2RenderingContext::POINTS, points);
+    }
+
+    fn set_brush(&mut self, brush : &Brush) {
+        match brush {
+            Brush::Color(r, g, b, a) => {
+                self.gl.uniform1ui(self.shader_info.u_brush_type.as_ref(), 1);
+                self.gl.uniform4f(self.shader_info.u_color.as_ref(), f32::to_owned(r), f32::to_owned(g), f32::to_owned(b), f32::to_owned(a));
+            },
+            Brush::LinearGradient(gradient) => self.set_gradient(2, gradient),
+            Brush::RadialGradient(gradient) => self.set_gradient(3, gradient),
+            Brush::ConicGradient(gradient) => self.set_gradient(4, gradient),
+        };
+    }
+}
+
+/**
+ * Vertex array reading positions of the attribute from the buffer
+ */
+fn create_vertex_array(gl : &WebGl2RenderingContext, attribute : u32, buffer : &WebGlBuffer) -> WebGlVertexArrayObject {
+    let vao = gl
+        .create_vertex_array()
+        .ok_or("Could not create vertex array object")
+        .unwrap();
+    gl.bind_vertex_array(Some(&vao));
+    gl.bind_buffer(WebGl2RenderingContext::ARRAY_BUFFER, Some(buffer));
+
+    gl.vertex_attrib_pointer_with_i32(
+        attribute,
+        COORDS_PER_VERTEX,
+        WebGl2RenderingContext::FLOAT,
+        false,
+        0,
+        0,
+    );
+    gl.enable_vertex_attrib_array(attribute);
+
+    vao
+}
@@ -0,0 +1,734 @@
+use std::{collections::HashMap, f32::consts::PI, primitive};
+
+use crate::base::log;
+use crate::data::{history::{Edit, History, DEFAULT_HISTORY_LIMIT}, svg::path_data::parse_path_data, Document, Node, NodeContent, NodeId, Shape, ShapeStroke};
+use crate::matrix::Matrix3x3;
+use crate::path::Path;
+use crate::renderer::{draw, draw_scene, image::{encode_png, ImageOptions}, webgl::WebGlBackend, Brush, ColorSpace, FillRule, LineCap, LineJoin, StrokeStyle, Gradient, GradientStop, Interpolation, Polygon, Spread, Primitive, Renderer, Triangles, TrianglesMode, P};
+use crate::{data, renderer, Orientation};
+use once_cell::sync::Lazy;
+use wasm_bindgen::prelude::*;
+use web_sys::{console::{time_end_with_label, time_with_label}, Event, WebGl2RenderingContext};
+use std::fmt::Write; // for write!
+extern crate console_error_panic_hook;
+
+/**
+ * Which brush is changed by the brush setters
+ */
+#[derive(Clone, Copy, PartialEq)]
+enum BrushTarget {
+    Fill, Stroke, Background
+}
+
+struct Context {
+    id : String,
+    brush : Option<Brush>,
+    stroke_brush : Option<Brush>,
+    stroke_style : StrokeStyle,
+    brush_target : BrushTarget,
+    /** brush the canvas is cleared with before drawing */
+    background : Brush,
+    renderer : Renderer<WebGlBackend>,
+    document : Document,
+    /** group which new shapes are added to */
+    current_group : NodeId,
+    history : History,
+    canvas_element : web_sys::HtmlCanvasElement
+}
+
+static mut CONTEXTS : Lazy<HashMap<String, Context>> = Lazy::new(||{
+    HashMap::new()
+});
+
+
+#[wasm_bindgen]
+pub fn initialize(canvas_id : &str) -> Result<(), JsValue> {
+
+    console_error_panic_hook::set_once();
+
+    let document = web_sys::window().unwrap().document().unwrap();
+    let canvas = document.get_element_by_id(canvas_id).unwrap();
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let context = canvas
+        .get_context("webgl2")?
+        .unwrap()
+        .dyn_into::<WebGl2RenderingContext>()?;
+    
+    let renderer = Renderer::new(WebGlBackend::create(context));
+    let background = renderer::BACKGROUND_COLOR;
+    let scene = Document::new();
+
+    let context = Context{
+        id : canvas_id.to_string(),
+        brush : Some(renderer::Brush::Color(1.0, 1.0, 1.0, 1.0)),
+        stroke_brush : None,
+        stroke_style : StrokeStyle::new(1.0),
+        brush_target : BrushTarget::Fill,
+        background : renderer::Brush::Color(background[0], background[1], background[2], background[3]),
+        renderer : renderer,
+        current_group : scene.root(),
+        document : scene,
+        history : History::new(DEFAULT_HISTORY_LIMIT),
+        canvas_element : canvas
+    };
+
+    unsafe {
+        CONTEXTS.insert(canvas_id.to_string(), context);
+    }
+
+
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn resize(canvas_id : &str, width : u32, height : u32) -> Result<(), JsValue> {
+
+    unsafe {
+        let option = CONTEXTS.get_mut(canvas_id);
+
+        match option {
+            Some(context) => {
+                context.renderer.backend_mut().resize_viewport(width as f32, height as f32);
+
+                context.canvas_element.set_width(width);
+                context.canvas_element.set_height(height);
+            },
+            None => ()
+        }
+
+    }
+
+
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn redraw(canvas_id : &str) -> Result<(), JsValue> {
+
+    render(get_context(canvas_id));
+
+    Ok(())
+}
+
+fn render(context : &mut Context) {
+    time_with_label("Render time");
+    draw(&mut context.renderer, &context.background);
+    time_end_with_label("Render time");
+}
+
+fn get_context(canvas_id : &str) -> &'static mut Context {
+    let option: Option<&mut Context>;
+    unsafe {
+        option = CONTEXTS.get_mut(canvas_id);
+    }
+    match option {
+        Some(context) => {
+            context
+        },
+        None => {panic!("No context with name {}", canvas_id)}
+    }
+}
+
+
+#[wasm_bindgen]
+pub struct JsPoint {
+    pub x : f64,
+    pub y : f64
+}
+
+
+#[wasm_bindgen]
+pub fn set_solid_color_brush(canvas_id : &str, r:f32, g:f32, b:f32, a:f32) {
+
+    let brush = renderer::Brush::Color(r, g, b, a);
+
+    change_brush(canvas_id, brush);
+    
+}
+
+pub fn change_brush(canvas_id : &str, brush : Brush) {
+    let context = get_context(canvas_id);
+
+    match context.brush_target {
+        BrushTarget::Fill => context.brush = Some(brush),
+        BrushTarget::Stroke => context.stroke_brush = Some(brush),
+        BrushTarget::Background => context.background = brush,
+    }
+}
+
+/**
+ * Selects whether the following brush changes apply to fills ("fill"), outlines ("stroke") or the background of the canvas ("background").
+ * Background takes effect on the next redraw, a transparent one shows the page under the canvas.
+ */
+#[wasm_bindgen]
+pub fn set_brush_target(canvas_id : &str, target : &str) -> Result<(), JsValue> {
+    let context = get_context(canvas_id);
+
+    context.brush_target = match target {
+        "fill" => BrushTarget::Fill,
+        "stroke" => BrushTarget::Stroke,
+        "background" => BrushTarget::Background,
+        _ => return Err(JsValue::from_str(&format!("Unknown brush target {}", target)))
+    };
+
+    Ok(())
+}
+
+/**
+ * Removes brush of the current target, so that following shapes are not filled or not stroked,
+ * or the background becomes transparent
+ */
+#[wasm_bindgen]
+pub fn clear_brush(canvas_id : &str) {
+    let context = get_context(canvas_id);
+
+    match context.brush_target {
+        BrushTarget::Fill => context.brush = None,
+        BrushTarget::Stroke => context.stroke_brush = None,
+        BrushTarget::Background => context.background = Brush::Color(0.0, 0.0, 0.0, 0.0),
+    }
+}
+
+/**
+ * Sets outline style of following shapes. Join is "miter" (default), "round" or "bevel",
+ * cap is "butt" (default), "round" or "square", and miter limit defaults to 4.
+ */
+#[wasm_bindgen]
+pub fn set_stroke_style(canvas_id : &str, width : f32, join : Option<String>, cap : Option<String>, miter_limit : Option<f32>) -> Result<(), JsValue> {
+    let context = get_context(canvas_id);
+
+    let mut style = StrokeStyle::new(width);
+    if let Some(name) = join {
+        style.join = LineJoin::from_name(&name).ok_or_else(|| JsValue::from_str(&format!("Unknown line join {}", name)))?;
+    }
+    if let Some(name) = cap {
+        style.cap = LineCap::from_name(&name).ok_or_else(|| JsValue::from_str(&format!("Unknown line cap {}", name)))?;
+    }
+    if let Some(limit) = miter_limit {
+        style.miter_limit = limit;
+    }
+    style.dash_array = std::mem::take(&mut context.stroke_style.dash_array);
+    style.dash_offset = context.stroke_style.dash_offset;
+
+    context.stroke_style = style;
+
+    Ok(())
+}
+
+/**
+ * Sets dash pattern of following outlines as alternating dash and gap lengths, an empty list makes them solid again.
+ * Offset shifts the start of the stroke into the pattern.
+ */
+#[wasm_bindgen]
+pub fn set_stroke_dash(canvas_id : &str, dashes : Vec<f32>, offset : f32) {
+    let context = get_context(canvas_id);
+
+    context.stroke_style.dash_array = dashes;
+    context.stroke_style.dash_offset = offset;
+}
+
+
+
+
+/**
+ * Sets the brush of the current target to a gradient along the line from (x1, y1) to (x2, y2), stops and options are read by `get_gradient`.
+ */
+#[wasm_bindgen]
+pub fn set_linear_gradient(canvas_id : &str, x1:f32, y1:f32, x2:f32, y2:f32, stops:Vec<f32>, transform : Option<Vec<f32>>, spread : Option<String>, interpolation : Option<String>, color_space : Option<String>) -> Result<(), JsValue> {
+    let gradient = get_gradient([x1, y1, 0.0, x2, y2, 0.0], stops, transform, spread, interpolation, color_space)?;
+    change_brush(canvas_id, Brush::LinearGradient(gradient));
+    Ok(())
+}
+
+/**
+ * Sets the brush of the current target to a gradient from the circle around (x1, y1) with radius r1 to the circle around (x2, y2)
+ * with radius r2, like `createRadialGradient` in Canvas2D, elliptical gradients are made by the transform.
+ * Stops and options are read by `get_gradient`.
+ */
+#[wasm_bindgen]
+pub fn set_radial_gradient(canvas_id : &str, x1:f32, y1:f32, r1:f32, x2:f32, y2:f32, r2:f32, stops:Vec<f32>, transform : Option<Vec<f32>>, spread : Option<String>, interpolation : Option<String>, color_space : Option<String>) -> Result<(), JsValue> {
+    let gradient = get_gradient([x1, y1, r1, x2, y2, r2], stops, transform, spread, interpolation, color_space)?;
+    change_brush(canvas_id, Brush::RadialGradient(gradient));
+    Ok(())
+}
+
+/**
+ * Sets the brush of the current target to a gradient turning around (x1, y1), stops and options are read by `get_gradient`.
+ */
+#[wasm_bindgen]
+pub fn set_conic_gradient(canvas_id : &str, x1:f32, y1:f32, x2:f32, y2:f32, stops:Vec<f32>, transform : Option<Vec<f32>>, spread : Option<String>, interpolation : Option<String>, color_space : Option<String>) -> Result<(), JsValue> {
+    let gradient = get_gradient([x1, y1, 0.0, x2, y2, 0.0], stops, transform, spread, interpolation, color_space)?;
+    change_brush(canvas_id, Brush::ConicGradient(gradient));
+    Ok(())
+}
+
+/**
+ * Gradient through the circles (x1, y1, r1) and (x2, y2, r2). Stops are given as a flat list of positions each followed by r, g, b and a,
+ * two stops at the same position make a hard edge.
+ * The transform places the gradient in the coordinates of the shape, given as the six numbers of the SVG `matrix(a b c d e f)`.
+ * Spread is "pad" (default), "repeat" or "reflect", and colours change between stops "linear" (default) or "smooth".
+ * Colours are mixed in the colour space named like in CSS, "srgb" (default), "srgb-linear", "oklab" or "oklch", optionally followed by
+ * "shorter hue" (default), "longer hue", "increasing hue" or "decreasing hue".
+ */
+fn get_gradient([x1, y1, r1, x2, y2, r2] : [f32; 6], stops : Vec<f32>, transform : Option<Vec<f32>>, spread : Option<String>, interpolation : Option<String>, color_space : Option<String>) -> Result<Gradient, JsValue> {
+    let transform = match transform.as_deref() {
+        None => None,
+        Some(&[a, b, c, d, e, f]) => Some(Matrix3x3::from_affine(a, b, c, d, e, f)),
+        Some(_) => return Err(JsValue::from_str("Gradient transform needs 6 numbers")),
+    };
+    let spread = match spread {
+        Some(name) => Spread::from_name(&name).ok_or_else(|| JsValue::from_str(&format!("Unknown gradient spread {}", name)))?,
+        None => Spread::Pad
+    };
+    let interpolation = match interpolation {
+        Some(name) => Interpolation::from_name(&name).ok_or_else(|| JsValue::from_str(&format!("Unknown gradient interpolation {}", name)))?,
+        None => Interpolation::Linear
+    };
+    let color_space = match color_space {
+        Some(name) => ColorSpace::from_name(&name).ok_or_else(|| JsValue::from_str(&format!("Unknown gradient colour space {}", name)))?,
+        None => ColorSpace::Srgb
+    };
+
+    Ok(Gradient{x1, y1, x2, y2, r1, r2, stops : get_gradient_stops(stops), spread, interpolation, color_space, transform})
+}
+
+pub fn get_gradient_stops(stops:Vec<f32>) -> Vec<GradientStop>  {
+    let mut gradient_stops = Vec::new();
+    for i in (0..stops.len()).skip(4).step_by(5) {
+        gradient_stops.push(GradientStop{
+            position : stops[i-4],
+            r : stops[i-3],
+            g : stops[i-2],
+            b : stops[i-1],
+            a : stops[i-0],
+        });
+    }
+    return gradient_stops;
+}
+
+
+
+/**
+ * Adds a polygon and returns its handle. Points are given as a flat list of coordinates, and `hole_indices` (if given)
+ * lists the indices of vertices where each of the holes begins, so the outer contour ends at the first of them.
+ * Winding of the points is detected automatically, an explicit "clockwise" or "counter-clockwise" orientation
+ * is only used for self-intersecting contours which enclose no total area.
+ * Fill rule is either "nonzero" (default) or "evenodd".
+ */
+#[wasm_bindgen]
+pub fn add_polygon(canvas_id : &str, orientation : Option<String>, points : Vec<f32>, hole_indices : Option<Vec<u32>>, fill_rule : Option<String>) -> Result<u32, JsValue> {
+    let context: &mut Context = get_context(canvas_id);
+
+    let fill_rule = get_fill_rule(fill_rule)?;
+    let path = get_polygon_path(orientation, &points, hole_indices, fill_rule)?;
+
+    Ok(add_shape(context, path, fill_rule))
+}
+
+/**
+ * Path of the polygon given the same way as to `add_polygon`
+ */
+fn get_polygon_path(orientation : Option<String>, points : &Vec<f32>, hole_indices : Option<Vec<u32>>, fill_rule : FillRule) -> Result<Path, JsValue> {
+    let (outer, holes) = get_contours(points, &hole_indices.unwrap_or_default());
+
+    let declared_orientation = match orientation.as_deref() {
+        Some("clockwise") | None => Orientation::Clockwise,
+        Some("counter-clockwise") => Orientation::CounterClockwise,
+        Some(name) => return Err(JsValue::from_str(&format!("Unknown orientation {}", name))),
+    };
+
+    let mut polygon = Polygon::new(outer, holes, fill_rule);
+
+    if (polygon.orientation == Orientation::Colinear) {
+        polygon.orientation = declared_orientation;
+    }
+
+    Ok(polygon.to_path())
+}
+
+/**
+ * Adds a path and returns its handle, filled according to the fill rule ("nonzero" by default, or "evenodd").
+ * Curves are approximated with the path's tolerance.
+ */
+#[wasm_bindgen]
+pub fn add_path(canvas_id : &str, path : &Path, fill_rule : Option<String>) -> Result<u32, JsValue> {
+    let context: &mut Context = get_context(canvas_id);
+
+    let fill_rule = get_fill_rule(fill_rule)?;
+
+    Ok(add_shape(context, path.clone(), fill_rule))
+}
+
+/**
+ * Adds a path given as SVG path data (the `d` attribute) and returns its handle, filled according to the fill rule
+ */
+#[wasm_bindgen]
+pub fn add_svg_path(canvas_id : &str, d : &str, fill_rule : Option<String>) -> Result<u32, JsValue> {
+    let context: &mut Context = get_context(canvas_id);
+
+    let fill_rule = get_fill_rule(fill_rule)?;
+    let path = parse_path_data(d).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    Ok(add_shape(context, path, fill_rule))
+}
+
+/**
+ * Removes shape or group with all of its content
+ */
+#[wasm_bindgen]
+pub fn remove_shape(canvas_id : &str, id : u32) -> Result<(), JsValue> {
+    let context: &mut Context = get_context(canvas_id);
+
+    let id = NodeId(id);
+    if (id == context.document.root()) {
+        return Err(JsValue::from_str("Root group can't be removed"));
+    }
+    get_node(context, id.0)?;
+
+    apply_edit(context, Edit::Remove(id))
+}
+
+/**
+ * Replaces outline of the shape with a polygon given the same way as to `add_polygon`, keeping its brushes and fill rule
+ */
+#[wasm_bindgen]
+pub fn update_shape_points(canvas_id : &str, id : u32, points : Vec<f32>, hole_indices : Option<Vec<u32>>) -> Result<(), JsValue> {
+    let context: &mut Context = get_context(canvas_id);
+
+    let mut shape = get_shape(context, id)?.clone();
+    shape.path = get_polygon_path(None, &points, hole_indices, shape.fill_rule)?;
+
+    apply_edit(context, Edit::SetShape(NodeId(id), shape))
+}
+
+/**
+ * Paints the fill or the outline of the shape, depending on the brush target, with the current brush.
+ * Outlines which had no brush get the current stroke style.
+ */
+#[wasm_bindgen]
+pub fn set_shape_brush(canvas_id : &str, id : u32) -> Result<(), JsValue> {
+    let context: &mut Context = get_context(canvas_id);
+
+    let (fill, stroke_brush, stroke_style) = (context.brush.clone(), context.stroke_brush.clone(), context.stroke_style.clone());
+    let target = context.brush_target;
+    let mut shape = get_shape(context, id)?.clone();
+
+    match target {
+        BrushTarget::Fill => shape.fill = fill,
+        BrushTarget::Stroke => {
+            let style = shape.stroke.take().map(|stroke| stroke.style).unwrap_or(stroke_style);
+            shape.stroke = stroke_brush.map(|brush| ShapeStroke{brush, style});
+        },
+        BrushTarget::Background => return Err(JsValue::from_str("Background brush can't be set on a shape")),
+    }
+
+    apply_edit(context, Edit::SetShape(NodeId(id), shape))
+}
+
+/**
+ * Shows or hides shape or group, hidden shapes keep their tesselation
+ */
+#[wasm_bindgen]
+pub fn set_shape_visible(canvas_id : &str, id : u32, visible : bool) -> Result<(), JsValue> {
+    let context: &mut Context = get_context(canvas_id);
+
+    get_node(context, id)?;
+
+    apply_edit(context, Edit::SetVisible(NodeId(id), visible))
+}
+
+/**
+ * Imports an SVG document as a new group of the current group and returns id of the group.
+ * Shapes are drawn with their own fills and strokes instead of the current brushes.
+ */
+#[wasm_bindgen]
+pub fn import_svg(canvas_id : &str, svg : &str) -> Result<u32, JsValue> {
+    let context: &mut Context = get_context(canvas_id);
+
+    let group = data::svg::import::import_svg(svg, &mut context.document, context.current_group).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    context.history.record(Edit::Remove(group));
+
+    update_renderer(context);
+
+    Ok(group.0)
+}
+
+/**
+ * Returns the shapes on the canvas as a standalone SVG document, including the current transform
+ */
+#[wasm_bindgen]
+pub fn export_svg(canvas_id : &str) -> String {
+    let context: &mut Context = get_context(canvas_id);
+
+    data::svg::export::export_svg(
+        &context.document,
+        context.canvas_element.width(),
+        context.canvas_element.height(),
+        context.renderer.transform(),
+        &context.background
+    )
+}
+
+/**
+ * Draws the canvas into a PNG image and returns its bytes. Size is given in canvas units and defaults to the size of the canvas,
+ * the scale gives pixels per unit. With a transparent background only the shapes are drawn.
+ */
+#[wasm_bindgen]
+pub fn export_png(canvas_id : &str, width : Option<f32>, height : Option<f32>, scale : Option<f32>, transparent : bool) -> Result<Vec<u8>, JsValue> {
+    let context: &mut Context = get_context(canvas_id);
+
+    let mut options = ImageOptions::new(
+        width.unwrap_or(context.canvas_element.width() as f32),
+        height.unwrap_or(context.canvas_element.height() as f32)
+    );
+    options.scale = scale.unwrap_or(1.0);
+    options.background = if (transparent) {None} else {Some(context.background.clone())};
+    let (pixel_width, pixel_height) = options.pixel_size();
+    if (pixel_width == 0 || pixel_height == 0) {
+        return Err(JsValue::from_str("Image has no pixels"));
+    }
+
+    let target = context.renderer.backend_mut().begin_offscreen(options.width, options.height, options.scale).map_err(|e| JsValue::from_str(&e))?;
+    // the debug overlay is only for the canvas, images get just the shapes
+    draw_scene(&mut context.renderer, &options.background_brush());
+    let pixels = context.renderer.backend_mut().read_offscreen(target).map_err(|e| JsValue::from_str(&e))?;
+
+    encode_png(pixel_width, pixel_height, &pixels).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/**
+ * Adds shape drawn with the current brushes and stroke style to the current group and returns its handle
+ */
+fn add_shape(context : &mut Context, path : Path, fill_rule : FillRule) -> u32 {
+    let shape = Shape{
+        path,
+        fill : context.brush.clone(),
+        fill_rule,
+        stroke : context.stroke_brush.as_ref().map(|brush| ShapeStroke{brush : brush.clone(), style : context.stroke_style.clone()}),
+    };
+
+    let id = context.document.add_shape(context.current_group, shape).unwrap();
+    context.history.record(Edit::Remove(id));
+
+    update_renderer(context);
+
+    id.0
+}
+
+/**
+ * Applies the edit to the document and records it in the history, shapes it changes are tesselated again
+ */
+fn apply_edit(context : &mut Context, edit : Edit) -> Result<(), JsValue> {
+    let changed = edit.changed_geometry();
+    if (!context.history.apply(&mut context.document, edit)) {
+        return Err(JsValue::from_str("Edit can't be applied to the document"));
+    }
+    if let Some(id) = changed {
+        context.renderer.invalidate(&context.document.subtree(id));
+    }
+    document_changed(context);
+
+    Ok(())
+}
+
+/**
+ * Brings the current group and the renderer in line with the document after nodes were changed, added or removed
+ */
+fn document_changed(context : &mut Context) {
+    if (!context.document.contains(context.current_group)) {
+        context.current_group = context.document.root();
+    }
+    update_renderer(context);
+}
+
+/**
+ * Tesselates new and changed shapes of the document
+ */
+fn update_renderer(context : &mut Context) {
+    time_with_label("Tesselation time");
+    context.renderer.update(&context.document);
+    time_end_with_label("Tesselation time");
+}
+
+fn get_node(context : &mut Context, id : u32) -> Result<&mut Node, JsValue> {
+    context.document.node_mut(NodeId(id)).ok_or_else(|| JsValue::from_str(&format!("No node with id {}", id)))
+}
+
+fn get_shape(context : &mut Context, id : u32) -> Result<&mut Shape, JsValue> {
+    match &mut get_node(context, id)?.content {
+        NodeContent::Shape(shape) => Ok(shape),
+        NodeContent::Group(_) => Err(JsValue::from_str(&format!("Node {} is not a shape", id)))
+    }
+}
+
+/**
+ * Adds an empty group to the current group and returns its id
+ */
+#[wasm_bindgen]
+pub fn add_group(canvas_id : &str, name : Option<String>) -> u32 {
+    let context: &mut Context = get_context(canvas_id);
+
+    // current group is always a group of the document
+    let id = context.document.add_group(context.current_group, name).unwrap();
+    context.history.record(Edit::Remove(id));
+
+    id.0
+}
+
+/**
+ * Selects group which the following shapes and groups are added to, the root group if no id is given
+ */
+#[wasm_bindgen]
+pub fn set_current_group(canvas_id : &str, id : Option<u32>) -> Result<(), JsValue> {
+    let context: &mut Context = get_context(canvas_id);
+
+    let group = match id {
+        Some(id) => match get_node(context, id)?.content {
+            NodeContent::Group(_) => NodeId(id),
+            NodeContent::Shape(_) => return Err(JsValue::from_str(&format!("Node {} is not a group", id)))
+        },
+        None => context.document.root()
+    };
+    context.current_group = group;
+
+    Ok(())
+}
+
+/**
+ * Sets transform of the node relative to its parent, given as the SVG `matrix(a b c d e f)`
+ */
+#[wasm_bindgen]
+pub fn set_node_transform(canvas_id : &str, id : u32, a : f32, b : f32, c : f32, d : f32, e : f32, f : f32) -> Result<(), JsValue> {
+    let context: &mut Context = get_context(canvas_id);
+
+    get_node(context, id)?;
+
+    apply_edit(context, Edit::SetTransform(NodeId(id), Matrix3x3::from_affine(a, b, c, d, e, f)))
+}
+
+#[wasm_bindgen]
+pub fn set_node_name(canvas_id : &str, id : u32, name : Option<String>) -> Result<(), JsValue> {
+    let context: &mut Context = get_context(canvas_id);
+
+    get_node(context, id)?;
+
+    apply_edit(context, Edit::SetName(NodeId(id), name))
+}
+
+/**
+ * Reverts the last change of the document and redraws the canvas, returns false if there was nothing to undo
+ */
+#[wasm_bindgen]
+pub fn undo(canvas_id : &str) -> bool {
+    let context: &mut Context = get_context(canvas_id);
+
+    match context.history.undo(&mut context.document) {
+        Some(changed) => {
+            history_changed(context, changed);
+            true
+        },
+        None => false
+    }
+}
+
+/**
+ * Makes the last undone change again and redraws the canvas, returns false if there was nothing to redo
+ */
+#[wasm_bindgen]
+pub fn redo(canvas_id : &str) -> bool {
+    let context: &mut Context = get_context(canvas_id);
+
+    match context.history.redo(&mut context.document) {
+        Some(changed) => {
+            history_changed(context, changed);
+            true
+        },
+        None => false
+    }
+}
+
+fn history_changed(context : &mut Context, changed : Vec<NodeId>) {
+    for id in changed {
+        context.renderer.invalidate(&context.document.subtree(id));
+    }
+    document_changed(context);
+    render(context);
+}
+
+/**
+ * Groups the following changes, so that they are undone at once. Transactions can be nested.
+ */
+#[wasm_bindgen]
+pub fn begin_transaction(canvas_id : &str) {
+    get_context(canvas_id).history.begin_transaction();
+}
+
+#[wasm_bindgen]
+pub fn end_transaction(canvas_id : &str) {
+    get_context(canvas_id).history.end_transaction();
+}
+
+/**
+ * Sets how many changes (or transactions) can be undone, 100 by default
+ */
+#[wasm_bindgen]
+pub fn set_history_limit(canvas_id : &str, limit : u32) {
+    get_context(canvas_id).history.set_limit(limit as usize);
+}
+
+fn get_fill_rule(fill_rule : Option<String>) -> Result<FillRule, JsValue> {
+    match fill_rule {
+        Some(name) => FillRule::from_name(&name).ok_or_else(|| JsValue::from_str(&format!("Unknown fill rule {}", name))),
+        None => Ok(FillRule::NonZero)
+    }
+}
+
+/**
+ * Splits flat list of coordinates into the outer contour and holes starting at given vertex indices
+ */
+fn get_contours(points : &Vec<f32>, hole_indices : &Vec<u32>) -> (Vec<P>, Vec<Vec<P>>) {
+    let mut contours : Vec<Vec<P>> = Vec::new();
+    let mut current : Vec<P> = Vec::new();
+    let mut holes = hole_indices.iter().peekable();
+
+    for (i, (x, y)) in points.iter().step_by(2).zip(points.iter().skip(1).step_by(2)).enumerate() {
+        if (holes.peek().is_some_and(|h| **h as usize == i)) {
+            holes.next();
+            contours.push(current);
+            current = Vec::new();
+        }
+        current.push(P::new(*x, *y));
+    }
+    contours.push(current);
+
+    let outer = contours.remove(0);
+    (outer, contours)
+}
+
+#[wasm_bindgen]
+pub fn set_transform(canvas_id : &str, c11 : f32, c12 : f32, c13 : f32, c21 : f32, c22 : f32, c23 : f32, c31 : f32, c32 : f32, c33 : f32) {
+    let context: &mut Context = get_context(canvas_id);
+
+    let transform_matrix = Matrix3x3::new(c11, c12, c13, c21, c22, c23, c31, c32, c33);
+
+    context.renderer.set_transform(transform_matrix);
+    
+}
+
+/**
+ * Turns a part of the debug overlay of the canvas on or off: "wireframe" of the triangles, "vertices", "bounds" of the shapes,
+ * or "modes" colouring triangle fans and strips. Takes effect on the next redraw.
+ */
+#[wasm_bindgen]
+pub fn set_debug_overlay(canvas_id : &str, name : &str, enabled : bool) -> Result<(), JsValue> {
+    let context: &mut Context = get_context(canvas_id);
+
+    let mut overlay = *context.renderer.debug_overlay();
+    if (!overlay.set(name, enabled)) {
+        return Err(JsValue::from_str(&format!("Unknown debug overlay {}", name)));
+    }
+    context.renderer.set_debug_overlay(overlay);
+
+    Ok(())
+}